@@ -1,14 +1,22 @@
 //! Module to handle CLI arguments parsing and execution.
 
+use core::num::{NonZeroU32, NonZeroUsize};
+use core::ops::Range;
+use core::str::FromStr;
+use core::time::Duration;
 use std::fs;
+use std::io::Write;
 
-use clap::{ArgGroup, Parser};
-use color_eyre::eyre::{Context as _, eyre};
+use clap::{ArgGroup, Parser, Subcommand};
+use color_eyre::eyre::{Context as _, ContextCompat as _, bail, eyre};
 
 use crate::Res;
 use crate::data::Data;
 use crate::dialog::Dialog;
-use crate::json::JsonArgs;
+use crate::json::{JsonArgs, Limit, Pace, Schedule};
+use crate::output::{Output, OutputArgs};
+use crate::pattern::PatternFormat;
+use crate::serve::ServeArgs;
 
 /// CLI to generate some fake data under JSON format.
 #[derive(Parser, Debug)]
@@ -17,45 +25,100 @@ use crate::json::JsonArgs;
         .args(["interactive", "pattern", "file", "data_type", "list_types", "values"])
 ))]
 #[command(group(ArgGroup::new("combinable").multiple(true)))]
+#[expect(clippy::struct_excessive_bools, reason = "flags of the cli")]
 pub struct CliArgs {
-    /// String to print after every output generation
-    #[arg(short, long, group = "combinable", default_value = "\n")]
-    after: String,
-    /// String to print before every output generation
-    #[arg(short, long, group = "combinable", default_value = "")]
-    before: String,
+    /// Number of records generated at once with `--rate`, e.g. `--rate 500/s
+    /// --burst 100` generates 100 records 5 times per second.
+    #[arg(long, default_value = "1", group = "combinable", requires = "rate")]
+    burst: NonZeroU32,
+    /// Subcommand to run instead of generating records.
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Number of times to repeat the output.
     #[arg(short, long, default_value_t = 1, group = "combinable")]
-    count: u32,
+    count: u64,
     /// Deprecated, use `--pattern` instead
     #[arg(short = 't', long = "type", group = "combinable", hide = true)]
     data_type: Option<String>,
     /// Debug errors with more precise information.
     #[arg(short, long, default_value_t = false, hide = true)]
     debug: bool,
+    /// Generate records for the given duration instead of `--count`, e.g.
+    /// '30s' or '5m'.
+    #[arg(long, group = "combinable", conflicts_with_all = ["count", "shard", "size"])]
+    duration: Option<humantime::Duration>,
     /// Path to the file containing the pattern to use to generate the data.
     /// Supports json, csv, tsv.
     #[arg(short, long, group = "combinable")]
     file: Option<String>,
+    /// Generate records until interrupted, instead of `--count`.
+    #[arg(
+        long,
+        default_value_t = false,
+        group = "combinable",
+        conflicts_with_all = ["count", "duration", "shard", "size"]
+    )]
+    forever: bool,
     /// Select the data type with a dialog and fuzzy search.
     #[arg(short, long, default_value_t = false, conflicts_with_all = ["combinable", "list_types", "values"])]
     interactive: bool,
+    /// Vary the intervals between the records of `--rate` randomly by up to
+    /// this percentage.
+    #[arg(
+        long,
+        default_value_t = 0,
+        group = "combinable",
+        requires = "rate",
+        value_parser = clap::value_parser!(u32).range(0..=100)
+    )]
+    jitter: u32,
     /// Deprecrated, use `--pattern` instead
     #[arg(short, long, hide = true)]
     json: Option<String>,
     /// List all available data types.
     #[arg(short, long="list", default_value_t = false, conflicts_with_all = ["combinable", "interactive", "values"])]
     list_types: bool,
+    /// Index of the first record to generate. With a seed, the records are
+    /// the same as the ones of a run without offset.
+    #[arg(long, default_value_t = 0, group = "combinable")]
+    offset: u64,
+    /// Options to choose how the generated data is written.
+    #[command(flatten)]
+    output: OutputArgs,
     /// Pass a pattern using the CLI argument instead of in a file. Supports
     /// json, csv, tsv.
     #[arg(short, long, group = "combinable")]
     pattern: Option<String>,
+    /// Format of the pattern given with `--pattern` or `--file`.
+    #[arg(long, value_enum, default_value_t, group = "combinable")]
+    pattern_format: PatternFormat,
+    /// Generate the records at the given rate, e.g. '500/s', '10/min' or
+    /// '100/10s', and write every record as soon as it is generated.
+    #[arg(long, group = "combinable")]
+    rate: Option<Rate>,
     /// Deprecrated, use `--file` instead
     #[arg(long, hide = true)]
     schema: Option<String>,
     /// Generate with a given random seed
     #[arg(short, long, group = "combinable")]
     seed: Option<u64>,
+    /// Only generate a part of the records, with the format 'index/total'
+    /// (e.g. '3/8' for the third of eight parts).
+    #[arg(long, group = "combinable")]
+    shard: Option<Shard>,
+    /// Generate records until the given number of bytes is written instead
+    /// of `--count`, e.g. '500MB' or '2GiB'.
+    #[arg(
+        long,
+        group = "combinable",
+        value_parser = byte_size,
+        conflicts_with_all = ["count", "post", "shard", "sqlite"]
+    )]
+    size: Option<u64>,
+    /// Number of threads used to generate the data. The output is the same
+    /// for any number of threads when a seed is given.
+    #[arg(long, default_value = "1", group = "combinable")]
+    threads: NonZeroUsize,
     /// Add custom data types, with the format 'Type:Value1|Value2'
     #[arg(short, long = "user", group = "combinable")]
     user_defined: Vec<String>,
@@ -68,32 +131,58 @@ impl CliArgs {
     /// Check if the sequence of commands given are meaningful
     pub fn dispatch(self) -> (bool, Res<Action>) {
         macro_rules! schema {
-            ($pat:expr) => {
-                Action::Schema {
-                    count: self.count,
-                    before: self.before,
-                    after: self.after,
+            ($pat:expr, $pattern_format:expr) => {{
+                let limit = match (self.size, self.duration) {
+                    (Some(size), _) => Limit::Size(size),
+                    (None, Some(duration)) => Limit::Duration(duration.into()),
+                    (None, None) if self.forever => Limit::Forever,
+                    (None, None) => Limit::Count(self.count),
+                };
+                let output = Output::from(self.output);
+                output.check_limit(limit).map(|()| Action::Schema {
+                    schedule: Schedule {
+                        limit,
+                        pace: self.rate.map(|rate| Pace {
+                            burst: self.burst,
+                            interval: rate.interval(self.burst),
+                            jitter: self.jitter,
+                        }),
+                    },
+                    offset: self.offset,
+                    shard: self.shard,
+                    output: Box::new(output),
                     user_defined: self.user_defined,
                     seed: self.seed,
                     pattern: $pat,
-                }
-            };
+                    pattern_format: $pattern_format,
+                    threads: self.threads,
+                })
+            }};
         }
 
         (
             self.debug,
-            if self.schema.is_some() {
+            if let Some(command) = self.command {
+                Ok(match command {
+                    Command::Serve(args) => Action::Serve(Box::new(args)),
+                })
+            } else if self.schema.is_some() {
                 Err(eyre!("Use of `--schema` is deprecated and was replaced by `--file`."))
             } else if self.json.is_some() {
                 Err(eyre!("Use of `--json` is deprecated and was replaced by `--pattern`."))
             } else if self.data_type.is_some() {
                 Err(eyre!("Use of `--type` is deprecated and was replaced by `--pattern`."))
             } else if let Some(pattern) = self.pattern {
-                Ok(schema!(pattern))
+                schema!(pattern, self.pattern_format)
             } else if let Some(file) = self.file {
+                let pattern_format = if self.pattern_format == PatternFormat::Auto {
+                    PatternFormat::from_path(&file)
+                } else {
+                    self.pattern_format
+                };
                 fs::read_to_string(&file)
                     .with_context(|| format!("Failed to read {file}"))
-                    .map(|content| schema!(content))
+                    .and_then(|content| schema!(content, pattern_format))
             } else if self.interactive {
                 Ok(Action::Interactive)
             } else if let Some(values) = self.values {
@@ -119,33 +208,184 @@ pub enum Action {
     ListValues(String),
     /// Schema to produce the random data.
     Schema {
-        /// Number of times to repeat the output.
-        count: u32,
-        /// String to print before every output generation
-        before: String,
-        /// String to print after every output generation
-        after: String,
+        /// When to stop generating records, and how fast to generate them.
+        schedule: Schedule,
+        /// Index of the first record to generate.
+        offset: u64,
+        /// Part of the records to generate.
+        shard: Option<Shard>,
+        /// Output stage, to write the records in the requested format.
+        output: Box<Output>,
         /// Pattern to use for data generation. Supports
         /// json, csv, tsv.
         pattern: String,
+        /// Format of the pattern.
+        pattern_format: PatternFormat,
         /// Add custom data types, with the format 'Type:Value1|Value2'
         user_defined: Vec<String>,
         /// Generate with a given random seed
         seed: Option<u64>,
+        /// Number of threads used to generate the data.
+        threads: NonZeroUsize,
     },
+    /// Serve generated records as a mock REST API.
+    Serve(Box<ServeArgs>),
 }
 
 impl Action {
-    /// Runs the appropriate action.
-    pub fn run(self) -> Res<String> {
+    /// Runs the appropriate action, writing its output to `writer`.
+    pub fn run<W: Write>(self, writer: &mut W) -> Res {
         let data = Data::new(vec![], None)?;
-        match self {
-            Self::Schema { count, before, after, pattern, user_defined, seed } =>
-                JsonArgs::new(before, after, count, pattern, Data::new(user_defined, seed)?)
-                    .generate(),
-            Self::Interactive => Dialog::generate(data),
-            Self::ListTypes => Ok(data.list().join("\n")),
-            Self::ListValues(ty) => data.values(&ty),
+        let content = match self {
+            Self::Schema {
+                schedule,
+                offset,
+                shard,
+                output,
+                pattern,
+                pattern_format,
+                user_defined,
+                seed,
+                threads,
+            } => {
+                let records = match schedule.limit {
+                    Limit::Count(count) => shard.unwrap_or_default().records(offset, count)?,
+                    Limit::Duration(_) | Limit::Forever | Limit::Size(_) => offset..u64::MAX,
+                };
+                // Parallel generation needs a seed for refs to be consistent
+                // between threads.
+                let run_seed = seed.or_else(|| (threads.get() > 1).then(rand::random));
+                let mut run_data = Data::new(user_defined, run_seed)?;
+                // Records generated apart from the previous ones can't be
+                // checked for uniqueness against them.
+                if shard.is_some() || offset > 0 || threads.get() > 1 {
+                    run_data = run_data.fork();
+                }
+                return JsonArgs::new(
+                    *output,
+                    records,
+                    schedule,
+                    pattern,
+                    pattern_format,
+                    run_data,
+                    threads,
+                )
+                .generate(writer);
+            }
+            Self::Serve(args) => return args.run(writer),
+            Self::Interactive => Dialog::generate(data)?,
+            Self::ListTypes => data.list().join("\n"),
+            Self::ListValues(ty) => data.values(&ty)?,
+        };
+        writeln!(writer, "{content}").context("Failed to write output")
+    }
+}
+
+/// Subcommands of the CLI.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Serve generated records as a mock REST API, on localhost.
+    Serve(ServeArgs),
+}
+
+/// Number of records to generate per period of time, e.g. `500/s`.
+#[derive(Clone, Copy, Debug)]
+pub struct Rate {
+    /// Number of records generated per period.
+    count: NonZeroU32,
+    /// Period of time.
+    period: Duration,
+}
+
+impl Rate {
+    /// Time between two bursts of `burst` records.
+    pub fn interval(self, burst: NonZeroU32) -> Duration {
+        self.period
+            .checked_div(self.count.get())
+            .unwrap_or_default()
+            .saturating_mul(burst.get())
+    }
+}
+
+impl FromStr for Rate {
+    type Err = color_eyre::Report;
+
+    fn from_str(value: &str) -> Res<Self> {
+        let Some((count, period)) = value.split_once('/') else {
+            bail!("Missing `/` in rate. Expected: count/period, e.g. 500/s")
+        };
+        // A period without a number is a single unit of time, e.g. `s`.
+        let single;
+        let full_period = if period.starts_with(|first: char| first.is_ascii_digit()) {
+            period
+        } else {
+            single = format!("1{period}");
+            &single
+        };
+        Ok(Self {
+            count: count
+                .parse()
+                .with_context(|| format!("Invalid rate count `{count}`"))?,
+            period: humantime::parse_duration(full_period)
+                .with_context(|| format!("Invalid rate period `{period}`"))?,
+        })
+    }
+}
+
+/// Part of the records to generate, to split a generation across processes.
+#[derive(Clone, Copy, Debug)]
+pub struct Shard {
+    /// Position of the shard, starting from 1.
+    index: u64,
+    /// Total number of shards.
+    total: u64,
+}
+
+impl Shard {
+    /// Computes the indices of the records of this shard, when `count`
+    /// records starting from `offset` are split between all the shards.
+    fn records(self, offset: u64, count: u64) -> Res<Range<u64>> {
+        let bound = |index: u64| {
+            u128::from(count)
+                .checked_mul(u128::from(index))
+                .and_then(|total| total.checked_div(u128::from(self.total)))
+                .and_then(|position| u64::try_from(position).ok())
+                .and_then(|position| position.checked_add(offset))
+                .context("Too many records to generate")
+        };
+        Ok(bound(self.index.saturating_sub(1))?..bound(self.index)?)
+    }
+}
+
+impl Default for Shard {
+    fn default() -> Self {
+        Self { index: 1, total: 1 }
+    }
+}
+
+impl FromStr for Shard {
+    type Err = color_eyre::Report;
+
+    fn from_str(value: &str) -> Res<Self> {
+        let Some((index, total)) = value.split_once('/') else {
+            bail!("Missing `/` in shard. Expected: index/total, e.g. 3/8")
+        };
+        let shard = Self {
+            index: index
+                .parse()
+                .with_context(|| format!("Invalid shard index `{index}`"))?,
+            total: total
+                .parse()
+                .with_context(|| format!("Invalid shard total `{total}`"))?,
+        };
+        if shard.index == 0 || shard.index > shard.total {
+            bail!("Shard index must be between 1 and {}, but got {}", shard.total, shard.index)
         }
+        Ok(shard)
     }
 }
+
+/// Parses a number of bytes, e.g. `500MB` or `2GiB`.
+fn byte_size(value: &str) -> Result<u64, parse_size::Error> {
+    parse_size::parse_size(value)
+}
@@ -1,21 +1,38 @@
 //! Define traits to apply the data generator on all sorts of types.
 
+use core::fmt::Write as _;
 use core::hash::{Hash, Hasher};
-use core::mem::discriminant;
+use core::mem::{self, discriminant};
 use std::collections::{HashMap, HashSet};
 
+use chrono::{DateTime, SecondsFormat};
 use color_eyre::eyre::{Context as _, ContextCompat as _, bail, eyre};
 use rand::distr::uniform::{SampleRange, SampleUniform};
 use random_data::DataType;
 use serde_json::{Number, Value};
 
 use crate::Res;
-use crate::data_generator::RandomDataGenerator;
+use crate::data_generator::{RandomDataGenerator, derive_seed};
 use crate::generator_trait::{Generator, NullableGenerator};
 
+/// Timestamp in milliseconds of 2100-01-01, the upper bound of generated
+/// dates.
+const MAX_DATE_MILLIS: i64 = 4_102_444_800_000;
+
 /// Contains the list of data types and the random generator to apply
 /// generators.
 pub struct Data {
+    /// Whether this handler was forked to generate records independently of
+    /// the previous ones, in parallel or from an offset.
+    ///
+    /// Unique data types can't be generated in that case, as the values
+    /// produced for the other records are unknown.
+    forked: bool,
+    /// Path of the field being generated, with the format
+    /// `.key[index].nested_key`.
+    path: String,
+    /// Index of the record being generated.
+    record: u64,
     /// Pseudo-random refs
     ///
     /// This represents data that is randomly generated once, then used in
@@ -23,6 +40,8 @@ pub struct Data {
     refs: HashMap<String, OutputData>,
     /// Radnom data generator
     rng: RandomDataGenerator,
+    /// Seed of the random data generator, if any
+    seed: Option<u64>,
     /// Data types that were required to be unique.
     uniq_types: HashMap<String, HashSet<OutputData>>,
     /// User-defined data types
@@ -30,6 +49,21 @@ pub struct Data {
 }
 
 impl Data {
+    /// Creates a new handler with the same data types and seed, to generate
+    /// records independently of the previous ones (e.g. on another thread).
+    pub fn fork(&self) -> Self {
+        Self {
+            forked: true,
+            path: String::new(),
+            record: 0,
+            refs: HashMap::new(),
+            rng: RandomDataGenerator::new(self.seed),
+            seed: self.seed,
+            uniq_types: HashMap::new(),
+            user_defined: self.user_defined.clone(),
+        }
+    }
+
     /// Generate non-nullable data of the provided data type.
     fn generate(&mut self, data_type: &str) -> Res<OutputData> {
         if let Some(parsed) = data_type.strip_suffix(']')
@@ -61,6 +95,15 @@ impl Data {
             OutputData::Int(self.random_range(0..=u64::MAX))
         } else if data_type == "Float" {
             OutputData::Float(self.random_range(0.0f64..=f64::MAX))
+        } else if data_type == "ObjectId" {
+            OutputData::String(format!("{:024x}", self.random_range(0..1u128 << 96u8)))
+        } else if data_type == "Date" {
+            let millis = self.random_range(0..MAX_DATE_MILLIS);
+            OutputData::String(
+                DateTime::from_timestamp_millis(millis)
+                    .with_context(|| format!("{millis} is not a valid timestamp"))?
+                    .to_rfc3339_opts(SecondsFormat::Millis, true),
+            )
         } else {
             OutputData::String(self.rng.random_value(
                 DataType::try_from(data_type).map_err(|()| eyre!("Type {data_type} not found"))?,
@@ -118,13 +161,25 @@ impl Data {
     }
 
     /// Generate random data with a given ref
+    ///
+    /// With a seed, the value of a ref is generated from its own random
+    /// generator, so that it doesn't depend on the record that needed it
+    /// first.
     fn generate_ref(&mut self, data_type: &str, ref_position: usize) -> Res<OutputData> {
         let (type_name, ref_name) = data_type.split_at(ref_position);
         let key = ref_name.get(1..).unwrap_or_default();
         if let Some(value) = self.refs.get(key) {
             Ok(value.to_owned())
         } else {
-            let value = self.generate(type_name)?;
+            let value = if let Some(seed) = self.seed {
+                let ref_rng = RandomDataGenerator::new(Some(derive_seed(seed, ref_name)));
+                let record_rng = mem::replace(&mut self.rng, ref_rng);
+                let value = self.generate(type_name);
+                self.rng = record_rng;
+                value?
+            } else {
+                self.generate(type_name)?
+            };
             self.refs.insert(key.to_owned(), value.clone());
             Ok(value)
         }
@@ -133,6 +188,11 @@ impl Data {
     /// Generate a data type that must be different at every generation.
     #[expect(clippy::unwrap_used, reason = "generate can't empty uniq_types")]
     fn generate_unique(&mut self, data_type: &str) -> Res<OutputData> {
+        if self.forked {
+            bail!(
+                "Unique data types (`{data_type}*`) can't be generated with multiple threads, an offset or a shard"
+            );
+        }
         if !self.uniq_types.contains_key(data_type) {
             let generated_data = self.generate(data_type)?;
             self.uniq_types
@@ -187,7 +247,11 @@ impl Data {
         }
 
         Ok(Self {
+            forked: false,
+            path: String::new(),
+            record: 0,
             rng: RandomDataGenerator::new(seed),
+            seed,
             user_defined,
             refs: HashMap::new(),
             uniq_types: HashMap::new(),
@@ -227,6 +291,15 @@ impl Data {
         self.rng.random_range(range)
     }
 
+    /// Prepares the random generator to generate the record at `index`.
+    ///
+    /// With a seed, every record has its own random stream, so a record
+    /// doesn't depend on the ones generated before it.
+    pub fn start_record(&mut self, index: u64) {
+        self.record = index;
+        self.rng.set_stream(index);
+    }
+
     /// List the possible values of a data-type
     pub fn values(&self, data_type: &str) -> Res<String> {
         if let Some(values) = self.user_defined.get(data_type) {
@@ -241,6 +314,48 @@ impl Data {
                 .map(|list| list.join("\n"))
         }
     }
+
+    /// Generates the value of the field `key` of the current object.
+    ///
+    /// With a seed, every field has its own random generator, derived from
+    /// the seed and the path of the field, so adding or removing a field from
+    /// the pattern doesn't change the values of the other fields.
+    pub fn with_field<T, F>(&mut self, key: &str, generate: F) -> Res<T>
+    where
+        F: FnOnce(&mut Self) -> Res<T>,
+    {
+        let Some(seed) = self.seed else {
+            return generate(self);
+        };
+        let parent_len = self.path.len();
+        self.path.push('.');
+        self.path.push_str(key);
+        let mut field_rng = RandomDataGenerator::new(Some(derive_seed(seed, &self.path)));
+        field_rng.set_stream(self.record);
+        let parent_rng = mem::replace(&mut self.rng, field_rng);
+        let value = generate(self);
+        self.rng = parent_rng;
+        self.path.truncate(parent_len);
+        value
+    }
+
+    /// Generates the element `index` of the current array.
+    ///
+    /// With a seed, the index is part of the path of the nested fields, so
+    /// that the objects of an array don't all get the same values.
+    pub fn with_item<T, F>(&mut self, index: usize, generate: F) -> Res<T>
+    where
+        F: FnOnce(&mut Self) -> Res<T>,
+    {
+        if self.seed.is_none() {
+            return generate(self);
+        }
+        let parent_len = self.path.len();
+        write!(self.path, "[{index}]").context("Failed to build the path of the field")?;
+        let value = generate(self);
+        self.path.truncate(parent_len);
+        value
+    }
 }
 
 impl Generator<OutputData> for String {
@@ -271,6 +386,20 @@ pub enum OutputData {
 
 impl Eq for OutputData {}
 
+/// Kind of [`OutputData`], to know the type of the generated values without
+/// generating them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    /// Kind of [`OutputData::Bool`]
+    Bool,
+    /// Kind of [`OutputData::Float`]
+    Float,
+    /// Kind of [`OutputData::Int`]
+    Int,
+    /// Kind of [`OutputData::String`]
+    String,
+}
+
 impl Hash for OutputData {
     fn hash<H: Hasher>(&self, state: &mut H) {
         discriminant(self).hash(state);
@@ -294,6 +423,53 @@ impl OutputData {
             Self::String(string) => string,
         }
     }
+
+    /// Kind of the data generated for a data type, without generating it.
+    ///
+    /// This follows the same rules as [`Data::generate_nullable`].
+    pub fn kind(data_type: &str) -> OutputKind {
+        let parsed = Self::type_name(data_type);
+        if let Some((min, max)) = parsed.split_once("..") {
+            if min.parse::<u64>().is_ok() && max.parse::<u64>().is_ok() {
+                OutputKind::Int
+            } else {
+                OutputKind::Float
+            }
+        } else if parsed.contains('|') {
+            OutputKind::String
+        } else {
+            match parsed {
+                "Bool" => OutputKind::Bool,
+                "Float" => OutputKind::Float,
+                "Int" => OutputKind::Int,
+                _ => OutputKind::String,
+            }
+        }
+    }
+
+    /// Largest integer generated for a data type, if it generates integers.
+    ///
+    /// This follows the same rules as [`Self::kind`].
+    pub fn max_int(data_type: &str) -> Option<u64> {
+        let parsed = Self::type_name(data_type);
+        match parsed.split_once("..") {
+            Some((min, max)) => {
+                min.parse::<u64>().ok()?;
+                max.parse::<u64>().ok()?.checked_sub(1)
+            }
+            None => (parsed == "Int").then_some(u64::MAX),
+        }
+    }
+
+    /// Name of a data type, without the `?`, `*` and `[ref]` suffixes.
+    pub fn type_name(data_type: &str) -> &str {
+        let nullable_stripped = data_type.strip_suffix('?').unwrap_or(data_type);
+        let ref_stripped = nullable_stripped
+            .strip_suffix(']')
+            .and_then(|parsed| parsed.get(..parsed.rfind('[')?))
+            .unwrap_or(nullable_stripped);
+        ref_stripped.strip_suffix('*').unwrap_or(ref_stripped)
+    }
 }
 
 impl TryFrom<OutputData> for Value {
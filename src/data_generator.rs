@@ -1,9 +1,9 @@
 //! Wrapper around [`DataGenerator`] for internal usage.
 
-use rand::Rng as _;
 use rand::distr::uniform::{SampleRange, SampleUniform};
-use rand::rngs::ThreadRng;
+use rand::rngs::StdRng;
 use rand::seq::IndexedRandom as _;
+use rand::{Rng as _, SeedableRng as _};
 use rand_chacha::ChaCha20Rng;
 use random_data::{DataGenerator, DataType};
 
@@ -12,7 +12,7 @@ use random_data::{DataGenerator, DataType};
 /// determinastic generator.
 pub enum RandomDataGenerator {
     /// Non-deterministic generator
-    NonDeterministic(DataGenerator<ThreadRng>),
+    NonDeterministic(Box<DataGenerator<StdRng>>),
     /// Determinastic with a seed
     Seeded(Box<DataGenerator<ChaCha20Rng>>),
 }
@@ -33,7 +33,7 @@ impl RandomDataGenerator {
     /// Creates a new [`RandomDataGenerator`] with a seed or not.
     pub fn new(seed: Option<u64>) -> Self {
         seed.map_or_else(
-            || Self::NonDeterministic(DataGenerator::default()),
+            || Self::NonDeterministic(Box::new(DataGenerator::from(StdRng::from_os_rng()))),
             |inner| Self::Seeded(Box::new(DataGenerator::new_with_seed(inner))),
         )
     }
@@ -65,4 +65,32 @@ impl RandomDataGenerator {
             Self::Seeded(data_generator) => data_type.random(data_generator),
         }
     }
+
+    /// Moves a seeded generator to the start of the given stream.
+    ///
+    /// Each record is generated from its own stream, so that the value of a
+    /// record only depends on the seed and its index. This does nothing for
+    /// non-deterministic generators.
+    pub fn set_stream(&mut self, stream: u64) {
+        if let Self::Seeded(generator) = self {
+            generator.rng().set_stream(stream);
+            generator.rng().set_word_pos(0);
+        }
+    }
+}
+
+/// Derives a new seed from a seed and a label, with the FNV-1a hash.
+///
+/// This is used to have independent random streams for values that must not
+/// depend on the record that generated them first.
+pub fn derive_seed(seed: u64, label: &str) -> u64 {
+    /// FNV-1a 64-bits prime
+    const PRIME: u64 = 0x0100_0000_01b3;
+    #[expect(clippy::little_endian_bytes, reason = "only needs to be stable")]
+    seed.to_le_bytes()
+        .iter()
+        .chain(label.as_bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+        })
 }
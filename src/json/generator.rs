@@ -1,7 +1,5 @@
 //! Implement the generator traits for JSON values
 
-use core::iter::repeat_with;
-
 use color_eyre::eyre::{Context as _, ContextCompat as _, bail};
 use serde_json::{Map, Value};
 
@@ -18,16 +16,17 @@ impl Generator<Value> for Map<String, Value> {
                 continue;
             }
 
-            let parsed_key = if let Some(parsed_key) = key.strip_suffix('?') {
-                if data.random_null() {
-                    continue;
+            let (parsed_key, nullable) = key
+                .strip_suffix('?')
+                .map_or((key.as_str(), false), |parsed_key| (parsed_key, true));
+            let generated_value = data.with_field(parsed_key, |field_data| {
+                if nullable && field_data.random_null() {
+                    return Ok(None);
                 }
-                parsed_key
-            } else {
-                key
-            };
-            if let Some(generated_value) = json_value.generate_nullable(data)? {
-                new_map.insert(parsed_key.to_owned(), generated_value);
+                json_value.generate_nullable(field_data)
+            })?;
+            if let Some(value) = generated_value {
+                new_map.insert(parsed_key.to_owned(), value);
             }
         }
         Ok(Value::Object(new_map))
@@ -62,8 +61,8 @@ impl Generator<Value> for Vec<Value> {
                 bail!("Bounds of range should be integers, but {value} is not a number"),
         };
 
-        repeat_with(|| array_item_type.generate(data))
-            .take(len)
+        (0..len)
+            .map(|index| data.with_item(index, |item_data| array_item_type.generate(item_data)))
             .collect()
     }
 }
@@ -2,50 +2,397 @@
 
 mod generator;
 
-use color_eyre::eyre::Context as _;
+use core::iter::repeat_with;
+use core::mem;
+use core::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use core::ops::{ControlFlow, Range};
+use core::time::Duration;
+use std::io::Write;
+use std::thread;
+use std::time::Instant;
+
+use color_eyre::eyre::{Context as _, ContextCompat as _, eyre};
 use serde_json::Value;
 
 use crate::Res;
 use crate::data::Data;
 use crate::generator_trait::NullableGenerator as _;
+use crate::output::{Output, Position};
+use crate::pattern::PatternFormat;
+
+/// Number of records generated by a thread before the output is written.
+const BATCH_SIZE: u64 = 1024;
 
 /// Arguments for generating JSON data based on a schema file.
 pub struct JsonArgs {
-    /// String to print after every data generation of the JSON schema.
-    after: String,
-    /// String to print before every data generation of the JSON schema.
-    before: String,
-    /// Number of times to repeat the JSON generation.
-    count: u32,
     /// Data generator
     data: Data,
     /// JSON schema content
     json: String,
+    /// How to write the generated records.
+    output: Output,
+    /// Format of the pattern.
+    pattern_format: PatternFormat,
+    /// Indices of the records to generate.
+    ///
+    /// With a seed, a record only depends on its index, so any range of
+    /// records can be generated without generating the previous ones.
+    records: Range<u64>,
+    /// When to stop generating records, and how fast to generate them.
+    schedule: Schedule,
+    /// Number of threads used to generate the records.
+    threads: NonZeroUsize,
 }
 
 impl JsonArgs {
     /// Generate the JSON data based on the schema file and the provided
     /// parameters.
-    pub fn generate(mut self) -> Res<String> {
-        let json: Value = serde_json::from_str(&self.json).context("Failed to deserialise json")?;
+    ///
+    /// Every record is written to `writer` (or to the database, or to the
+    /// endpoint) as soon as it is generated, so memory usage doesn't grow
+    /// with the number of records.
+    pub fn generate<W: Write>(self, writer: &mut W) -> Res {
+        let Self { mut data, json, mut output, pattern_format, records, schedule, threads } = self;
+        let Schedule { limit, pace } = schedule;
+        let (pattern, default_format) = pattern_format.parse(&json)?;
+        output.set_default_format(default_format);
+
+        let start = Instant::now();
+        let chunk_size = output.chunk_size();
+        let count = match limit {
+            Limit::Count(count) => count,
+            Limit::Duration(_) | Limit::Forever | Limit::Size(_) =>
+                records.end.saturating_sub(records.start),
+        };
+        let generation =
+            Generation { chunk_size, count, pace, pattern: &pattern, records, threads };
+        if let Some(mut database) = output.database(&pattern)? {
+            generation.for_each_record(
+                &mut data,
+                |value, _| Ok(value),
+                |value| {
+                    database.insert(&value)?;
+                    Ok(limit.flow(start, 0))
+                },
+            )?;
+            return database.commit();
+        }
+        if let Some(mut endpoint) = output.endpoint()? {
+            generation.for_each_record(
+                &mut data,
+                |value, _| Ok(value.to_string()),
+                |record| {
+                    endpoint.push(record)?;
+                    Ok(limit.flow(start, 0))
+                },
+            )?;
+            return endpoint.finish(writer);
+        }
 
-        let mut generated_data = String::new();
-        let len = self.before.len().saturating_add(self.after.len());
-        for _ in 0..self.count {
-            let generate_json = json.generate_nullable(&mut self.data)?.unwrap_or_default();
-            let generate_json_str =
-                serde_json::to_string_pretty(&generate_json).context("Failed to serialise json")?;
-            generated_data.reserve(generate_json_str.len().saturating_add(len));
-            generated_data.push_str(&self.before);
-            generated_data.push_str(&generate_json_str);
-            generated_data.push_str(&self.after);
+        output.prepare(&pattern)?;
+        let mut destination = output.destination(writer)?;
+        // With a split output, nothing is written before the first file is
+        // opened.
+        let mut started = chunk_size.is_none();
+        if let Some(mut columnar) = output.columnar(&pattern)? {
+            generation.for_each_record(
+                &mut data,
+                |value, position| Ok((output.path(&value, position)?, value)),
+                |(path, value)| {
+                    if let Some(file_path) = path {
+                        if started {
+                            let next = output
+                                .columnar(&pattern)?
+                                .context("Expected a columnar format")?;
+                            mem::replace(&mut columnar, next).finish(&mut destination)?;
+                        }
+                        destination.open(&file_path)?;
+                        started = true;
+                    }
+                    columnar.push(&value, &mut destination)?;
+                    Ok(limit.flow(start, destination.written()))
+                },
+            )?;
+            if started {
+                columnar.finish(&mut destination)?;
+            }
+            return destination.finish();
         }
 
-        Ok(generated_data)
+        if started {
+            output.write_header(&mut destination)?;
+        }
+        // Number of records written in the current file.
+        let mut in_file = 0u64;
+        generation.for_each_record(
+            &mut data,
+            |value, position| {
+                let mut buffer = vec![];
+                output.write_record(&value, position, &mut buffer)?;
+                Ok((output.path(&value, position)?, buffer))
+            },
+            |(path, buffer)| {
+                if let Some(file_path) = path {
+                    if started {
+                        output.write_footer(&mut destination, in_file)?;
+                    }
+                    destination.open(&file_path)?;
+                    output.write_header(&mut destination)?;
+                    started = true;
+                    in_file = 0;
+                }
+                destination
+                    .write_all(&buffer)
+                    .context("Failed to write output")?;
+                in_file = in_file.saturating_add(1);
+                // Paced records are consumed as soon as they are written.
+                if pace.is_some() {
+                    destination.flush().context("Failed to write output")?;
+                }
+                Ok(limit.flow(start, destination.written()))
+            },
+        )?;
+        if started {
+            output.write_footer(&mut destination, in_file)?;
+        }
+        destination.finish()
     }
 
     /// Create a new instance of `JsonArgs` with the provided parameters.
-    pub const fn new(before: String, after: String, count: u32, json: String, data: Data) -> Self {
-        Self { after, before, count, data, json }
+    pub const fn new(
+        output: Output,
+        records: Range<u64>,
+        schedule: Schedule,
+        json: String,
+        pattern_format: PatternFormat,
+        data: Data,
+        threads: NonZeroUsize,
+    ) -> Self {
+        Self { data, json, output, pattern_format, records, schedule, threads }
+    }
+}
+
+/// When to stop generating records.
+#[derive(Clone, Copy, Debug)]
+pub enum Limit {
+    /// Generate all the records of the range, out of the given number of
+    /// records of the whole generation, given by `--count`.
+    Count(u64),
+    /// Stop once the duration has elapsed, given by `--duration`.
+    Duration(Duration),
+    /// Never stop, with `--forever`.
+    Forever,
+    /// Stop once this number of bytes is written, given by `--size`.
+    Size(u64),
+}
+
+impl Limit {
+    /// Whether to continue generating records, when they were generated since
+    /// `start` and `written` bytes were written.
+    fn flow(self, start: Instant, written: u64) -> ControlFlow<()> {
+        let reached = match self {
+            Self::Count(_) | Self::Forever => false,
+            Self::Duration(duration) => start.elapsed() >= duration,
+            Self::Size(size) => written >= size,
+        };
+        if reached {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+/// Pace of the generation, to generate records at a given rate.
+#[derive(Clone, Copy, Debug)]
+pub struct Pace {
+    /// Number of records generated at once.
+    pub burst: NonZeroU32,
+    /// Time between two bursts of records.
+    pub interval: Duration,
+    /// Maximum variation of the intervals, in percent.
+    pub jitter: u32,
+}
+
+/// State of a paced generation.
+pub struct Pacer {
+    /// Time at which the next burst of records is due.
+    due: Instant,
+    /// Number of records given so far.
+    emitted: u64,
+    /// Pace of the generation.
+    pace: Pace,
+}
+
+impl Pacer {
+    /// Starts a paced generation, with the first burst of records due now.
+    pub fn new(pace: Pace) -> Self {
+        Self { due: Instant::now(), emitted: 0, pace }
     }
+
+    /// Waits until the next record is due.
+    ///
+    /// If the generation is late, the next burst is scheduled from now, so
+    /// that the records aren't sent all at once to catch up.
+    pub fn wait(&mut self) {
+        let starts_burst = self.emitted % NonZeroU64::from(self.pace.burst) == 0;
+        self.emitted = self.emitted.saturating_add(1);
+        if !starts_burst {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(delay) = self.due.checked_duration_since(now) {
+            thread::sleep(delay);
+        }
+        let spread = self
+            .pace
+            .interval
+            .saturating_mul(self.pace.jitter)
+            .checked_div(100)
+            .unwrap_or_default();
+        let interval = self
+            .pace
+            .interval
+            .saturating_sub(spread)
+            .saturating_add(rand::random_range(Duration::ZERO..=spread.saturating_mul(2)));
+        let start = self.due.max(now);
+        self.due = start.checked_add(interval).unwrap_or(start);
+    }
+}
+
+/// When to stop generating records, and how fast to generate them.
+#[derive(Clone, Copy, Debug)]
+pub struct Schedule {
+    /// When to stop generating records, before the end of the records.
+    pub limit: Limit,
+    /// Pace of the generation, as fast as possible if [`None`].
+    pub pace: Option<Pace>,
+}
+
+/// Parameters of the generation of the records of a pattern.
+struct Generation<'pattern> {
+    /// Number of records written in every file, if the output is split.
+    chunk_size: Option<NonZeroU64>,
+    /// Number of records of the whole generation, including the other shards.
+    count: u64,
+    /// Pace of the generation, as fast as possible if [`None`].
+    pace: Option<Pace>,
+    /// Parsed pattern of the records.
+    pattern: &'pattern Value,
+    /// Indices of the records to generate.
+    records: Range<u64>,
+    /// Number of threads used to generate the records.
+    threads: NonZeroUsize,
+}
+
+impl Generation<'_> {
+    /// Generates the records, and gives them to `consume` in order, until it
+    /// breaks.
+    ///
+    /// Records are first given to `produce`, that can be called on multiple
+    /// threads, for instance to serialise them. With a pace, `consume` waits
+    /// until every record is due.
+    fn for_each_record<T, P, C>(&self, data: &mut Data, produce: P, mut consume: C) -> Res
+    where
+        T: Send,
+        P: Fn(Value, Position) -> Res<T> + Sync,
+        C: FnMut(T) -> Res<ControlFlow<()>>,
+    {
+        let mut pacer = self.pace.map(Pacer::new);
+        let mut paced_consume = |produced| {
+            if let Some(due) = &mut pacer {
+                due.wait();
+            }
+            consume(produced)
+        };
+        if self.threads.get() > 1 {
+            return self.for_each_record_parallel(data, &produce, paced_consume);
+        }
+        for index in self.records.clone() {
+            let value = generate_record(self.pattern, data, index)?;
+            if paced_consume(produce(value, self.position(index))?)?.is_break() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Generates the records by batches on [`Self::threads`] threads.
+    ///
+    /// Each thread generates and produces a contiguous range of records, and
+    /// the results are consumed in order, so the output is the same as with a
+    /// single thread.
+    fn for_each_record_parallel<T, P, C>(&self, data: &Data, produce: &P, mut consume: C) -> Res
+    where
+        T: Send,
+        P: Fn(Value, Position) -> Res<T> + Sync,
+        C: FnMut(T) -> Res<ControlFlow<()>>,
+    {
+        let threads = u64::try_from(self.threads.get()).context("Too many threads")?;
+        let mut workers = repeat_with(|| data.fork())
+            .take(self.threads.get())
+            .collect::<Vec<_>>();
+        let mut start = self.records.start;
+        while start < self.records.end {
+            let batches = thread::scope(|scope| {
+                let handles = workers
+                    .iter_mut()
+                    .zip(0u64..)
+                    .map(|(worker_data, worker)| {
+                        let first = start.saturating_add(worker.saturating_mul(BATCH_SIZE));
+                        let end = first.saturating_add(BATCH_SIZE).min(self.records.end);
+                        scope.spawn(move || -> Res<Vec<T>> {
+                            (first..end)
+                                .map(|index| {
+                                    let value = generate_record(self.pattern, worker_data, index)?;
+                                    produce(value, self.position(index))
+                                })
+                                .collect()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .map_err(|_err| eyre!("A generation thread panicked"))?
+                    })
+                    .collect::<Res<Vec<_>>>()
+            })?;
+            for produced in batches.into_iter().flatten() {
+                if consume(produced)?.is_break() {
+                    return Ok(());
+                }
+            }
+            start = start.saturating_add(threads.saturating_mul(BATCH_SIZE));
+        }
+        Ok(())
+    }
+
+    /// Position of the record at `index` in the output.
+    ///
+    /// If the output is split, the position is relative to the file of the
+    /// record.
+    fn position(&self, index: u64) -> Position {
+        let generated = index.saturating_sub(self.records.start);
+        let last_generated = index.saturating_add(1) == self.records.end;
+        let (chunk, written, last) =
+            self.chunk_size
+                .map_or((0, generated, last_generated), |size| {
+                    let written = generated.checked_rem(size.get()).unwrap_or_default();
+                    (
+                        generated.checked_div(size.get()).unwrap_or_default(),
+                        written,
+                        last_generated || written.saturating_add(1) == size.get(),
+                    )
+                });
+        Position { chunk, count: self.count, index, last, written }
+    }
+}
+
+/// Generates the record at position `index` of a pattern.
+pub fn generate_record(pattern: &Value, data: &mut Data, index: u64) -> Res<Value> {
+    data.start_record(index);
+    Ok(pattern.generate_nullable(data)?.unwrap_or_default())
 }
@@ -1,19 +1,25 @@
 //! CLI to generate some fake data under JSON format.
 
+extern crate alloc;
+
 mod clap;
 mod data;
 mod data_generator;
 mod dialog;
 mod generator_trait;
 mod json;
+mod output;
+mod pattern;
+mod serve;
 #[cfg(test)]
 mod tests;
 
+use std::io::{self, BufWriter, Write as _};
 use std::process::ExitCode;
 
 use ::clap::Parser as _;
 
-use crate::clap::{Action, CliArgs};
+use crate::clap::CliArgs;
 
 /// Colour eyre result short-hand that doesn't conflict with [`Result`]
 type Res<T = ()> = color_eyre::Result<T>;
@@ -21,13 +27,11 @@ type Res<T = ()> = color_eyre::Result<T>;
 fn main() -> Res<ExitCode> {
     color_eyre::install()?;
     let (debug, action) = CliArgs::parse().dispatch();
-    let res = action.and_then(Action::run);
-    #[expect(clippy::print_stdout, clippy::print_stderr, reason = "it's a cli")]
-    match res {
-        Ok(content) => {
-            println!("{}", content.trim());
-            Ok(ExitCode::SUCCESS)
-        }
+    let mut writer = BufWriter::new(io::stdout().lock());
+    let res = action.and_then(|act| act.run(&mut writer));
+    #[expect(clippy::print_stderr, reason = "it's a cli")]
+    match res.and_then(|()| writer.flush().map_err(Into::into)) {
+        Ok(()) => Ok(ExitCode::SUCCESS),
         Err(err) =>
             if debug {
                 Err(err)
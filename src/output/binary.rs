@@ -0,0 +1,53 @@
+//! Write the generated records in the binary encodings `MessagePack` and
+//! CBOR.
+
+use clap::ValueEnum;
+use color_eyre::eyre::Context as _;
+use serde_json::Value;
+
+use crate::Res;
+
+/// How to separate the encoded records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Framing {
+    /// Write the records one after the other, as a stream of values.
+    #[default]
+    Concatenated,
+    /// Write the length of every record before it, as a 4-byte big-endian
+    /// unsigned integer.
+    LengthDelimited,
+}
+
+impl Framing {
+    /// Frames an encoded record.
+    #[expect(clippy::big_endian_bytes, reason = "lengths are big endian")]
+    pub fn frame(self, encoded: Vec<u8>) -> Res<Vec<u8>> {
+        match self {
+            Self::Concatenated => Ok(encoded),
+            Self::LengthDelimited => {
+                let mut framed = u32::try_from(encoded.len())
+                    .context("Record too large to be length-delimited")?
+                    .to_be_bytes()
+                    .to_vec();
+                framed.extend(encoded);
+                Ok(framed)
+            }
+        }
+    }
+}
+
+/// Encodes a generated record in CBOR.
+///
+/// Integers, floats and strings keep their types.
+pub fn cbor(record: &Value) -> Res<Vec<u8>> {
+    let mut encoded = vec![];
+    ciborium::into_writer(record, &mut encoded).context("Failed to serialise cbor")?;
+    Ok(encoded)
+}
+
+/// Encodes a generated record in `MessagePack`.
+///
+/// Integers, floats and strings keep their types.
+pub fn msgpack(record: &Value) -> Res<Vec<u8>> {
+    rmp_serde::to_vec_named(record).context("Failed to serialise msgpack")
+}
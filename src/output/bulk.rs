@@ -0,0 +1,56 @@
+//! Write the generated records in the NDJSON format of the `_bulk` API of
+//! Elasticsearch and `OpenSearch`.
+
+use color_eyre::eyre::{Context as _, bail};
+use serde_json::{Map, Value, json};
+
+use crate::Res;
+
+/// Settings to write records for the `_bulk` API.
+pub struct Bulk<'args> {
+    /// Field of the records whose value is used as the `_id` of the
+    /// documents.
+    pub id_field: Option<&'args str>,
+    /// Index in which the documents are indexed.
+    pub index: &'args str,
+}
+
+impl Bulk<'_> {
+    /// Formats a generated record, as an action line followed by the
+    /// document.
+    ///
+    /// The `_id` is omitted if the id field was omitted from the record. A
+    /// field named `_id` is removed from the document, as it can't be part of
+    /// the source of a document.
+    pub fn record(&self, record: &Value) -> Res<String> {
+        let mut document = record.clone();
+        let removed_id = document
+            .as_object_mut()
+            .and_then(|object| object.remove("_id"));
+        let mut action = Map::new();
+        action.insert("_index".to_owned(), Value::from(self.index));
+        if let Some(id_field) = self.id_field {
+            let field = if id_field == "_id" {
+                removed_id
+            } else {
+                document.get(id_field).cloned()
+            };
+            let id = match field {
+                None | Some(Value::Null) => None,
+                Some(Value::String(string)) => Some(string),
+                Some(Value::Number(number)) => Some(number.to_string()),
+                Some(other @ (Value::Bool(_) | Value::Array(_) | Value::Object(_))) =>
+                    bail!("{other} can't be used as an `_id`, use a string or a number"),
+            };
+            if let Some(id_value) = id {
+                action.insert("_id".to_owned(), Value::String(id_value));
+            }
+        }
+        let mut lines = serde_json::to_string(&json!({ "index": action }))
+            .context("Failed to serialise json")?;
+        lines.push('\n');
+        lines.push_str(&serde_json::to_string(&document).context("Failed to serialise json")?);
+        lines.push('\n');
+        Ok(lines)
+    }
+}
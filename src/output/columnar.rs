@@ -0,0 +1,184 @@
+//! Write the generated records in the columnar formats Apache Parquet and
+//! Arrow IPC.
+
+use alloc::sync::Arc;
+use core::{mem, slice};
+use std::io::Write;
+
+use arrow_ipc::writer::FileWriter;
+use arrow_json::ReaderBuilder;
+use arrow_json::reader::Decoder;
+use arrow_schema::{DataType, Field, Fields, Schema};
+use color_eyre::eyre::Context as _;
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use serde_json::{Map, Value};
+
+use crate::Res;
+use crate::data::{OutputData, OutputKind};
+use crate::output::flatten::parse_key;
+
+/// Number of records in a Parquet row group or in an Arrow record batch.
+const ROW_GROUP_SIZE: usize = 50_000;
+
+/// Columnar file in which the records are written.
+///
+/// The files are written in memory, and the written bytes are moved to the
+/// output after every row group.
+enum File {
+    /// Arrow IPC file.
+    Arrow(FileWriter<Vec<u8>>),
+    /// Apache Parquet file.
+    Parquet(ArrowWriter<Vec<u8>>),
+}
+
+/// Writes the generated records in a columnar format, by row groups.
+pub struct Columnar {
+    /// Converts the generated records to Arrow record batches.
+    decoder: Decoder,
+    /// File in which the record batches are written.
+    file: File,
+}
+
+impl Columnar {
+    /// Prepares an Arrow IPC file for the records of the given pattern.
+    pub fn arrow(pattern: &Value) -> Res<Self> {
+        let (schema, decoder) = schema(pattern)?;
+        let file = FileWriter::try_new(vec![], &schema).context("Failed to create arrow file")?;
+        Ok(Self { decoder, file: File::Arrow(file) })
+    }
+
+    /// Writes the remaining records and the end of the file.
+    pub fn finish<W: Write>(mut self, writer: &mut W) -> Res {
+        self.write_row_group(writer)?;
+        match &mut self.file {
+            File::Arrow(file) => file.finish().context("Failed to write arrow file")?,
+            File::Parquet(file) => drop(file.finish().context("Failed to write parquet file")?),
+        }
+        self.write_buffer(writer)
+    }
+
+    /// Prepares a Parquet file for the records of the given pattern.
+    pub fn parquet(pattern: &Value) -> Res<Self> {
+        let (schema, decoder) = schema(pattern)?;
+        let properties = WriterProperties::builder()
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let file = ArrowWriter::try_new(vec![], Arc::new(schema), Some(properties))
+            .context("Failed to create parquet file")?;
+        Ok(Self { decoder, file: File::Parquet(file) })
+    }
+
+    /// Adds a generated record to the current row group, and writes the row
+    /// group if it is full.
+    pub fn push<W: Write>(&mut self, record: &Value, writer: &mut W) -> Res {
+        self.decoder
+            .serialize(slice::from_ref(record))
+            .context("Generated record doesn't match the schema of the pattern")?;
+        if self.decoder.len() >= ROW_GROUP_SIZE {
+            self.write_row_group(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the bytes written in the file to the output.
+    fn write_buffer<W: Write>(&mut self, writer: &mut W) -> Res {
+        let buffer = match &mut self.file {
+            File::Arrow(file) => file.get_mut(),
+            File::Parquet(file) => file.inner_mut(),
+        };
+        writer
+            .write_all(&mem::take(buffer))
+            .context("Failed to write output")
+    }
+
+    /// Writes the records that were pushed since the last row group.
+    fn write_row_group<W: Write>(&mut self, writer: &mut W) -> Res {
+        let Some(batch) = self
+            .decoder
+            .flush()
+            .context("Failed to convert the records to arrow")?
+        else {
+            return Ok(());
+        };
+        match &mut self.file {
+            File::Arrow(file) => file.write(&batch).context("Failed to write arrow file")?,
+            File::Parquet(file) => {
+                file.write(&batch).context("Failed to write parquet file")?;
+                file.flush().context("Failed to write parquet file")?;
+            }
+        }
+        self.write_buffer(writer)
+    }
+}
+
+/// Arrow type of the values generated for a pattern.
+///
+/// `literal` is `true` if the value was marked with `!`.
+fn data_type(pattern: &Value, literal: bool) -> DataType {
+    match pattern {
+        Value::Object(object) => DataType::Struct(fields(object, literal)),
+        Value::Array(items) => DataType::List(Arc::new(Field::new_list_field(
+            items
+                .first()
+                .map_or(DataType::Null, |item| data_type(item, literal)),
+            true,
+        ))),
+        Value::String(data_type) if !literal => match OutputData::kind(data_type) {
+            OutputKind::Bool => DataType::Boolean,
+            OutputKind::Float => DataType::Float64,
+            OutputKind::Int => DataType::UInt64,
+            OutputKind::String => DataType::Utf8,
+        },
+        Value::Number(number) if number.is_u64() => DataType::UInt64,
+        Value::Number(number) if number.is_i64() => DataType::Int64,
+        Value::Number(_) => DataType::Float64,
+        Value::Bool(_) => DataType::Boolean,
+        Value::Null => DataType::Null,
+        Value::String(_) => DataType::Utf8,
+    }
+}
+
+/// Arrow fields of the records generated for an object of the pattern.
+///
+/// Fields are nullable if they were marked with `?`.
+fn fields(object: &Map<String, Value>, literal: bool) -> Fields {
+    object
+        .iter()
+        .map(|(key, value)| {
+            let (name, child_literal, nullable) = parse_key(key, literal, false);
+            Field::new(
+                name,
+                data_type(value, child_literal),
+                nullable
+                    || value.is_null()
+                    || (!child_literal
+                        && value
+                            .as_str()
+                            .is_some_and(|data_type| data_type.ends_with('?'))),
+            )
+        })
+        .collect()
+}
+
+/// Infers the Arrow schema of the records generated for a pattern, and
+/// creates a decoder to convert these records to Arrow record batches.
+///
+/// If the pattern isn't an object, the records are in a single column named
+/// `value`.
+fn schema(pattern: &Value) -> Res<(Schema, Decoder)> {
+    let (schema, builder) = if let Value::Object(object) = pattern {
+        let schema = Schema::new(fields(object, false));
+        let builder = ReaderBuilder::new(Arc::new(schema.clone()));
+        (schema, builder)
+    } else {
+        let field = Field::new("value", data_type(pattern, false), true);
+        let builder = ReaderBuilder::new_with_field(field.clone());
+        (Schema::new([Arc::new(field)]), builder)
+    };
+    let decoder = builder
+        .with_batch_size(ROW_GROUP_SIZE)
+        .build_decoder()
+        .context("Failed to infer the schema of the pattern")?;
+    Ok((schema, decoder))
+}
@@ -0,0 +1,85 @@
+//! Compress the output while it is written.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use color_eyre::eyre::Context as _;
+use flate2::write::GzEncoder;
+
+use crate::Res;
+
+/// Compression of the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// Gzip, e.g. for `.json.gz` files.
+    Gzip,
+    /// Zstandard, e.g. for `.jsonl.zst` files.
+    Zstd,
+}
+
+impl Compression {
+    /// Compression of a file, inferred from its extension.
+    pub fn infer(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// Writer that compresses what is written to an inner writer.
+pub enum Encoder<W: Write> {
+    /// Gzip encoder.
+    Gzip(GzEncoder<W>),
+    /// Writes without compression.
+    Plain(W),
+    /// Zstandard encoder.
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes the end of the compressed stream, and flushes the inner
+    /// writer.
+    pub fn finish(self) -> Res {
+        let mut writer = match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Plain(writer) => Ok(writer),
+            Self::Zstd(encoder) => encoder.finish(),
+        }
+        .context("Failed to compress output")?;
+        writer.flush().context("Failed to write output")
+    }
+
+    /// Compresses what is written to `writer` with `compression`, if any.
+    pub fn new(writer: W, compression: Option<Compression>) -> Res<Self> {
+        Ok(match compression {
+            Some(Compression::Gzip) =>
+                Self::Gzip(GzEncoder::new(writer, flate2::Compression::default())),
+            Some(Compression::Zstd) => Self::Zstd(
+                zstd::Encoder::new(writer, zstd::DEFAULT_COMPRESSION_LEVEL)
+                    .context("Failed to start compression")?,
+            ),
+            None => Self::Plain(writer),
+        })
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Plain(writer) => writer.flush(),
+            Self::Zstd(encoder) => encoder.flush(),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Plain(writer) => writer.write(buf),
+            Self::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+}
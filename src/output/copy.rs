@@ -0,0 +1,120 @@
+//! Write the generated records in the formats of `PostgreSQL`'s `COPY ... FROM
+//! STDIN`.
+
+use color_eyre::eyre::{Context as _, ContextCompat as _, bail};
+use serde_json::Value;
+
+use crate::Res;
+use crate::data::OutputKind;
+use crate::output::flatten::{Column, ColumnKind};
+use crate::output::sql::Dialect;
+
+/// Signature, flags and header extension length of the binary format.
+pub const BINARY_HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+
+/// Trailer of the binary format.
+pub const BINARY_TRAILER: &[u8] = &[0xff, 0xff];
+
+/// Formats a row of the binary format for a generated record.
+///
+/// Integers are written as `bigint`, floats as `double precision`, booleans
+/// as `boolean`, nested objects and arrays as `jsonb` and the rest as `text`.
+#[expect(clippy::big_endian_bytes, reason = "the binary format is big endian")]
+pub fn binary_row(columns: &[Column], record: &Value) -> Res<Vec<u8>> {
+    let mut row = i16::try_from(columns.len())
+        .context("Too many columns for the binary format")?
+        .to_be_bytes()
+        .to_vec();
+    for column in columns {
+        let field = match (column.get(record), column.kind) {
+            (None | Some(Value::Null), _) => {
+                row.extend_from_slice(&(-1i32).to_be_bytes());
+                continue;
+            }
+            (Some(Value::Number(number)), ColumnKind::Data(OutputKind::Float)) => number
+                .as_f64()
+                .with_context(|| format!("{number} is not a valid float"))?
+                .to_be_bytes()
+                .to_vec(),
+            (Some(Value::Number(number)), _) => number
+                .as_i64()
+                .with_context(|| {
+                    format!("{number} doesn't fit in a bigint, give a smaller range like `0..1000`")
+                })?
+                .to_be_bytes()
+                .to_vec(),
+            (Some(Value::Bool(bool)), _) => vec![u8::from(*bool)],
+            (Some(Value::String(string)), _) => string.as_bytes().to_vec(),
+            (Some(json @ (Value::Array(_) | Value::Object(_))), _) => {
+                let mut jsonb = vec![1];
+                jsonb.extend_from_slice(json.to_string().as_bytes());
+                jsonb
+            }
+        };
+        row.extend_from_slice(
+            &i32::try_from(field.len())
+                .context("Value too large for the binary format")?
+                .to_be_bytes(),
+        );
+        row.extend_from_slice(&field);
+    }
+    Ok(row)
+}
+
+/// Checks that the integers of the columns fit in a `bigint`, before any row
+/// of the binary format is written.
+pub fn check_bigints(columns: &[Column]) -> Res {
+    for column in columns {
+        if let Some(max) = column.max_int
+            && i64::try_from(max).is_err()
+        {
+            bail!(
+                "The integers of `{}` can be up to {max}, which doesn't fit in a bigint, give a \
+                 smaller range like `0..1000`",
+                column.name()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Escapes a value for the text format.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Formats the `COPY` command that precedes the rows in the text format, to
+/// be able to pipe the output to `psql`.
+pub fn text_header(table: &str, columns: &[Column]) -> String {
+    format!(
+        "COPY {} ({}) FROM STDIN;\n",
+        Dialect::Postgres.identifier(table),
+        columns
+            .iter()
+            .map(|column| Dialect::Postgres.identifier(&column.name()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Formats a row of the text format for a generated record.
+///
+/// Omitted fields are written as `\N`, and nested objects and arrays as
+/// JSON.
+pub fn text_row(columns: &[Column], record: &Value) -> String {
+    let mut row = columns
+        .iter()
+        .map(|column| match column.get(record) {
+            None => "\\N".to_owned(),
+            Some(Value::String(string)) => escape(string),
+            Some(value) => escape(&value.to_string()),
+        })
+        .collect::<Vec<_>>()
+        .join("\t");
+    row.push('\n');
+    row
+}
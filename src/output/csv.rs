@@ -0,0 +1,139 @@
+//! Write the generated records as CSV or TSV, with one column per field.
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::output::flatten::Column;
+
+/// How to write arrays in a tabular output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ArrayPolicy {
+    /// Write one row per element of the array, repeating the other columns.
+    Explode,
+    /// Write the elements of the array separated by `--array-separator`.
+    Join,
+    /// Write the array as JSON.
+    #[default]
+    Json,
+}
+
+/// Settings to write records as CSV or TSV.
+pub struct Table<'args> {
+    /// Separator between the elements of arrays, with [`ArrayPolicy::Join`].
+    pub array_separator: &'args str,
+    /// How to write arrays.
+    pub arrays: ArrayPolicy,
+    /// Delimiter between two columns: `,` for CSV and `\t` for TSV.
+    pub delimiter: char,
+    /// String to write for omitted fields.
+    pub null: &'args str,
+}
+
+impl Table<'_> {
+    /// Formats a non-null cell.
+    fn cell(&self, value: &Value) -> String {
+        match value {
+            Value::Array(values) if self.arrays == ArrayPolicy::Join => self.escape(
+                &values
+                    .iter()
+                    .map(|item| match item {
+                        Value::String(string) => string.to_owned(),
+                        Value::Null
+                        | Value::Bool(_)
+                        | Value::Number(_)
+                        | Value::Array(_)
+                        | Value::Object(_) => item.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(self.array_separator),
+            ),
+            Value::String(string) => self.escape(string),
+            Value::Null
+            | Value::Bool(_)
+            | Value::Number(_)
+            | Value::Array(_)
+            | Value::Object(_) => self.escape(&value.to_string()),
+        }
+    }
+
+    /// Escapes a cell to be written in the table.
+    ///
+    /// For CSV, cells are quoted if they contain a delimiter, a quote, a new
+    /// line or if they are empty, to be different from omitted fields. For
+    /// TSV, tabs, new lines and backslashes are escaped with a backslash.
+    fn escape(&self, cell: &str) -> String {
+        if self.delimiter == '\t' {
+            cell.replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r")
+        } else if cell.is_empty()
+            || cell.contains([self.delimiter, '"', '\n', '\r'])
+            || cell == self.null
+        {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.to_owned()
+        }
+    }
+
+    /// Formats the header of the table, containing the names of the columns.
+    pub fn header(&self, columns: &[Column]) -> String {
+        let mut header = columns
+            .iter()
+            .map(|column| self.escape(&column.name()))
+            .collect::<Vec<_>>()
+            .join(&self.delimiter.to_string());
+        header.push('\n');
+        header
+    }
+
+    /// Formats the rows of the table for a generated record.
+    ///
+    /// There is only one row, unless arrays are exploded: then there is a row
+    /// for every combination of the elements of the arrays.
+    pub fn rows(&self, columns: &[Column], record: &Value) -> String {
+        let cells = columns
+            .iter()
+            .map(|column| match column.get(record) {
+                Some(Value::Array(values))
+                    if self.arrays == ArrayPolicy::Explode && !values.is_empty() =>
+                    values.iter().map(|value| self.cell(value)).collect(),
+                Some(Value::Array(_)) if self.arrays == ArrayPolicy::Explode =>
+                    vec![self.null.to_owned()],
+                cell => vec![cell.map_or_else(|| self.null.to_owned(), |value| self.cell(value))],
+            })
+            .collect::<Vec<Vec<_>>>();
+        let mut rows = String::new();
+        let mut indices = vec![0; cells.len()];
+        loop {
+            let row = cells
+                .iter()
+                .zip(&indices)
+                .filter_map(|(column_cells, index)| column_cells.get(*index).map(String::as_str))
+                .collect::<Vec<_>>();
+            rows.push_str(&row.join(&self.delimiter.to_string()));
+            rows.push('\n');
+            if !next_row(&mut indices, &cells) {
+                return rows;
+            }
+        }
+    }
+}
+
+/// Moves `indices` to the next combination of cells, when arrays are
+/// exploded.
+///
+/// # Returns
+///
+/// Returns `false` if all the combinations were already produced.
+fn next_row(indices: &mut [usize], cells: &[Vec<String>]) -> bool {
+    for (index, column_cells) in indices.iter_mut().zip(cells).rev() {
+        *index = index.saturating_add(1);
+        if *index < column_cells.len() {
+            return true;
+        }
+        *index = 0;
+    }
+    false
+}
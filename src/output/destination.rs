@@ -0,0 +1,189 @@
+//! Choose where the generated records are written: the output of the
+//! command, a file, or a file per chunk of records.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{Context as _, ContextCompat as _, bail, eyre};
+use serde_json::Value;
+
+use crate::Res;
+use crate::output::Position;
+use crate::output::compress::{Compression, Encoder};
+
+/// Writer to the output of the command, or to the file that is currently
+/// written.
+pub struct Destination<'writer, W: Write> {
+    /// Compression of the files.
+    compression: Option<Compression>,
+    /// File that is currently written, if the records are written to files.
+    file: Option<Encoder<BufWriter<File>>>,
+    /// Whether every record has its own file, that must not exist yet, so
+    /// that records with the same path don't overwrite each other.
+    per_record: bool,
+    /// Output of the command.
+    writer: Encoder<&'writer mut W>,
+    /// Number of bytes written, before compression.
+    written: u64,
+}
+
+impl<'writer, W: Write> Destination<'writer, W> {
+    /// Ends the compressed streams, and flushes the file that is currently
+    /// written.
+    pub fn finish(self) -> Res {
+        if let Some(file) = self.file {
+            file.finish()?;
+        }
+        self.writer.finish()
+    }
+
+    /// Writes to `writer`, or to the files opened with [`Self::open`] if
+    /// `to_files` is `true`, compressed with `compression`.
+    pub fn new(
+        writer: &'writer mut W,
+        compression: Option<Compression>,
+        to_files: bool,
+        per_record: bool,
+    ) -> Res<Self> {
+        Ok(Self {
+            compression,
+            file: None,
+            per_record,
+            writer: Encoder::new(writer, compression.filter(|_| !to_files))?,
+            written: 0,
+        })
+    }
+
+    /// Closes the file that is currently written, and writes the next
+    /// records to the file at `path`.
+    ///
+    /// The parent directories are created if they don't exist. With a file per
+    /// record, the file must not exist yet.
+    pub fn open(&mut self, path: &Path) -> Res {
+        if let Some(file) = self.file.take() {
+            file.finish()?;
+        }
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+        let file = if self.per_record {
+            File::create_new(path).map_err(|err| {
+                if err.kind() == io::ErrorKind::AlreadyExists {
+                    eyre!(
+                        "Output file {} already exists, give a path that is different for every \
+                         record",
+                        path.display()
+                    )
+                } else {
+                    eyre!(err).wrap_err(format!("Failed to create output file {}", path.display()))
+                }
+            })?
+        } else {
+            File::create(path)
+                .with_context(|| format!("Failed to create output file {}", path.display()))?
+        };
+        self.file = Some(Encoder::new(BufWriter::new(file), self.compression)?);
+        Ok(())
+    }
+
+    /// Number of bytes written to the output or to all the files, before
+    /// compression.
+    pub const fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+impl<W: Write> Write for Destination<'_, W> {
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => self.writer.flush(),
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = match &mut self.file {
+            Some(file) => file.write(buf),
+            None => self.writer.write(buf),
+        }?;
+        self.written = self
+            .written
+            .saturating_add(u64::try_from(written).unwrap_or(u64::MAX));
+        Ok(written)
+    }
+}
+
+/// Path of the chunk number `chunk` of the output file `path`, e.g.
+/// `out-00002.jsonl.zst` for `out.jsonl.zst`.
+pub fn chunk_path(path: &Path, chunk: u64) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    // The first character is skipped not to take hidden files for extensions.
+    let name = file_name
+        .char_indices()
+        .skip(1)
+        .find(|&(_, character)| character == '.')
+        .and_then(|(dot, _)| file_name.split_at_checked(dot))
+        .map_or_else(
+            || format!("{file_name}-{chunk:05}"),
+            |(stem, extensions)| format!("{stem}-{chunk:05}{extensions}"),
+        );
+    path.with_file_name(name)
+}
+
+/// Whether `path` contains placeholders, to write every record to its own
+/// file.
+pub fn is_template(path: &Path) -> bool {
+    path.to_string_lossy().contains('{')
+}
+
+/// Path of the file of a record, from a path with placeholders.
+///
+/// `{i}` is replaced by the index of the record, and `{field}` by the value
+/// of a field of the record, with `.` to access nested fields. The values
+/// can't leave the directory of the template: their separators are replaced
+/// by `_`, and they can't make a segment empty, `.` or `..`.
+pub fn record_path(template: &Path, record: &Value, position: Position) -> Res<PathBuf> {
+    let template_str = template.to_string_lossy();
+    let mut path = String::new();
+    let mut rest = template_str.as_ref();
+    while let Some((before, after)) = rest.split_once('{') {
+        let (name, after_name) = after
+            .split_once('}')
+            .with_context(|| format!("Missing `}}` in output path {template_str}"))?;
+        path.push_str(before);
+        if name == "i" {
+            path.push_str(&position.index.to_string());
+        } else {
+            let value = name
+                .split('.')
+                .try_fold(record, |value, key| value.get(key))
+                .with_context(|| format!("Field `{name}` of the output path is missing"))?;
+            let text = match value {
+                Value::String(string) => string.to_owned(),
+                Value::Bool(_) | Value::Number(_) => value.to_string(),
+                Value::Null | Value::Array(_) | Value::Object(_) =>
+                    bail!("Field `{name}` of the output path must be a string or a number"),
+            };
+            path.push_str(&text.replace(['/', '\\'], "_"));
+        }
+        rest = after_name;
+    }
+    path.push_str(rest);
+    // The values can't contain separators, so the segments of the path match
+    // the ones of the template.
+    for (segment, template_segment) in path.split(['/', '\\']).zip(template_str.split(['/', '\\']))
+    {
+        if template_segment.contains('{') && matches!(segment, "" | "." | "..") {
+            bail!(
+                "The output path {path} of record {} has an empty, `.` or `..` segment",
+                position.index
+            );
+        }
+    }
+    Ok(PathBuf::from(path))
+}
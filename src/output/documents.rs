@@ -0,0 +1,50 @@
+//! Write the generated records as YAML or TOML documents.
+
+use color_eyre::eyre::{Context as _, bail};
+use serde_json::{Map, Number, Value};
+
+use crate::Res;
+
+/// Converts the integers that are too large for TOML to floats, and removes
+/// the `null` values, as TOML has no null.
+fn fit_toml(value: &mut Value) {
+    match value {
+        Value::Number(number) if number.as_i64().is_none() && number.is_u64() =>
+            if let Some(float) = number.as_f64().and_then(Number::from_f64) {
+                *number = float;
+            },
+        Value::Array(items) => {
+            items.retain(|item| !item.is_null());
+            items.iter_mut().for_each(fit_toml);
+        }
+        Value::Object(object) => {
+            object.retain(|_, field| !field.is_null());
+            object.values_mut().for_each(fit_toml);
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => (),
+    }
+}
+
+/// Formats a generated record as an element of an array of tables named
+/// `name`, so that the records form a valid TOML document.
+///
+/// TOML integers are 64-bit signed integers, so larger integers are written
+/// as floats, and `null` fields are omitted.
+pub fn toml(name: &str, record: &Value) -> Res<String> {
+    if !record.is_object() {
+        bail!("Records must be objects to be written in TOML, but {record} isn't an object");
+    }
+    let mut fitted = record.clone();
+    fit_toml(&mut fitted);
+    let mut document = Map::new();
+    document.insert(name.to_owned(), Value::Array(vec![fitted]));
+    let mut table = toml::to_string(&document).context("Failed to serialise toml")?;
+    table.push('\n');
+    Ok(table)
+}
+
+/// Formats a generated record as an item of a sequence, so that the records
+/// form a valid YAML document.
+pub fn yaml(record: &Value) -> Res<String> {
+    serde_norway::to_string(&[record]).context("Failed to serialise yaml")
+}
@@ -0,0 +1,159 @@
+//! Flatten the generated records into rows of columns, for tabular formats.
+
+use serde_json::Value;
+
+use crate::data::{OutputData, OutputKind};
+
+/// Column of a tabular output, that contains the value at a path of keys in
+/// the generated record.
+pub struct Column {
+    /// Kind of the values of the column.
+    pub kind: ColumnKind,
+    /// Largest integer of the column, if it contains integers.
+    pub max_int: Option<i128>,
+    /// Whether the values of the column can be omitted.
+    pub nullable: bool,
+    /// Keys to access the value of the column, from the root of the record.
+    pub path: Vec<String>,
+}
+
+/// Kind of the values of a column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnKind {
+    /// Generated data of the given kind.
+    Data(OutputKind),
+    /// Arrays and objects.
+    Json,
+}
+
+impl ColumnKind {
+    /// Kind of a literal value, given with `!`.
+    fn of_literal(literal: &Value) -> Self {
+        match literal {
+            Value::Bool(_) => Self::Data(OutputKind::Bool),
+            Value::Number(number) if number.is_u64() || number.is_i64() =>
+                Self::Data(OutputKind::Int),
+            Value::Number(_) => Self::Data(OutputKind::Float),
+            Value::Null | Value::String(_) => Self::Data(OutputKind::String),
+            Value::Array(_) | Value::Object(_) => Self::Json,
+        }
+    }
+
+    /// Kind of the values generated for a pattern.
+    fn of_pattern(pattern: &Value) -> Self {
+        match pattern {
+            Value::String(data_type) => Self::Data(OutputData::kind(data_type)),
+            Value::Array(_) | Value::Object(_) => Self::Json,
+            Value::Null | Value::Bool(_) | Value::Number(_) => Self::Data(OutputKind::String),
+        }
+    }
+}
+
+impl Column {
+    /// Gets the value of this column in a generated record.
+    ///
+    /// # Returns
+    ///
+    /// Returns [`None`] if the field was omitted, e.g. because it was
+    /// optional.
+    pub fn get<'value>(&self, record: &'value Value) -> Option<&'value Value> {
+        self.path
+            .iter()
+            .try_fold(record, |value, key| value.get(key))
+            .filter(|value| !value.is_null())
+    }
+
+    /// Name of the column, with the nested keys separated by dots.
+    pub fn name(&self) -> String {
+        if self.path.is_empty() {
+            "value".to_owned()
+        } else {
+            self.path.join(".")
+        }
+    }
+}
+
+/// State of the traversal of a pattern, to list its columns.
+struct Walker {
+    /// Columns found so far.
+    columns: Vec<Column>,
+    /// Whether the fields of nested objects have their own columns.
+    nested: bool,
+    /// Path of the value being traversed.
+    path: Vec<String>,
+}
+
+impl Walker {
+    /// Pushes the columns of the value `pattern`, found at [`Self::path`].
+    ///
+    /// `literal` is `true` if the value was marked with `!`, and `nullable`
+    /// if it, or one of its parents, was marked with `?`.
+    fn push(&mut self, pattern: &Value, literal: bool, nullable: bool) {
+        match pattern {
+            Value::Object(object) if self.nested || self.path.is_empty() =>
+                for (key, value) in object {
+                    let (parsed_key, child_literal, child_nullable) =
+                        parse_key(key, literal, nullable);
+                    self.path.push(parsed_key.to_owned());
+                    self.push(value, child_literal, child_nullable);
+                    self.path.pop();
+                },
+            Value::Null
+            | Value::Bool(_)
+            | Value::Number(_)
+            | Value::String(_)
+            | Value::Array(_)
+            | Value::Object(_) => self.columns.push(Column {
+                kind: if literal {
+                    ColumnKind::of_literal(pattern)
+                } else {
+                    ColumnKind::of_pattern(pattern)
+                },
+                max_int: if literal {
+                    pattern
+                        .as_i64()
+                        .map(i128::from)
+                        .or_else(|| pattern.as_u64().map(i128::from))
+                } else {
+                    pattern
+                        .as_str()
+                        .and_then(OutputData::max_int)
+                        .map(i128::from)
+                },
+                nullable: nullable
+                    || (!literal
+                        && pattern
+                            .as_str()
+                            .is_some_and(|data_type| data_type.ends_with('?'))),
+                path: self.path.clone(),
+            }),
+        }
+    }
+}
+
+/// Lists the columns of the records generated from a pattern.
+///
+/// If `nested` is `true`, the fields of the nested objects have their own
+/// columns, e.g. `address.city`. Otherwise, nested objects are in a single
+/// column.
+pub fn columns(pattern: &Value, nested: bool) -> Vec<Column> {
+    let mut walker = Walker { columns: vec![], nested, path: vec![] };
+    walker.push(pattern, false, false);
+    walker.columns
+}
+
+/// Strips the `!` and `?` suffixes of a key of an object of the pattern.
+///
+/// `literal` and `nullable` are those of the object, and the returned ones are
+/// those of the value of the key. Keys of literal objects are kept intact.
+pub fn parse_key(key: &str, literal: bool, nullable: bool) -> (&str, bool, bool) {
+    if literal {
+        (key, true, nullable)
+    } else if let Some(parsed_key) = key.strip_suffix('!') {
+        (parsed_key, true, nullable)
+    } else if let Some(parsed_key) = key.strip_suffix('?') {
+        (parsed_key, false, true)
+    } else {
+        (key, false, nullable)
+    }
+}
@@ -0,0 +1,630 @@
+//! Output stage of the generation, to write the generated records in the
+//! requested format.
+
+mod binary;
+mod bulk;
+mod columnar;
+mod compress;
+mod copy;
+mod csv;
+mod destination;
+mod documents;
+mod flatten;
+mod mongo;
+mod post;
+mod sql;
+mod sqlite;
+mod template;
+mod xml;
+
+use core::num::{NonZeroU64, NonZeroUsize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+use color_eyre::eyre::{Context as _, ContextCompat as _, bail};
+use serde::Serialize as _;
+use serde_json::Value;
+use serde_json::ser::{CompactFormatter, PrettyFormatter, Serializer};
+
+use crate::Res;
+use crate::json::Limit;
+use crate::output::binary::Framing;
+use crate::output::bulk::Bulk;
+use crate::output::columnar::Columnar;
+use crate::output::compress::Compression;
+use crate::output::csv::{ArrayPolicy, Table};
+use crate::output::destination::Destination;
+use crate::output::flatten::{Column, columns};
+use crate::output::post::Endpoint;
+use crate::output::sql::{Dialect, Insert};
+use crate::output::sqlite::Database;
+use crate::output::template::Template;
+use crate::output::xml::{Xml, XmlFields};
+
+/// Format of the generated output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Arrow IPC file, with a column per field.
+    Arrow,
+    /// CBOR encoded records, framed according to `--framing`.
+    Cbor,
+    /// Comma-separated values, with a header and a column per field.
+    Csv,
+    /// NDJSON for the `_bulk` API of Elasticsearch and `OpenSearch`, in the
+    /// index given by `--index`.
+    EsBulk,
+    /// Records surrounded by `--before` and `--after`.
+    #[default]
+    Json,
+    /// A valid JSON array containing all the records.
+    JsonArray,
+    /// `MongoDB` Extended JSON, one document per line, for `mongoimport`.
+    Mongo,
+    /// `MessagePack` encoded records, framed according to `--framing`.
+    Msgpack,
+    /// One compact JSON record per line.
+    Ndjson,
+    /// Apache Parquet file, with a column per field.
+    Parquet,
+    /// Text format of `PostgreSQL`'s `COPY ... FROM STDIN`, preceded by the
+    /// `COPY` command if `--table` is given.
+    PgCopy,
+    /// Binary format of `PostgreSQL`'s `COPY ... FROM STDIN`.
+    PgCopyBinary,
+    /// SQL `INSERT` statements, in the table given by `--table`.
+    Sql,
+    /// Records rendered with the template given by `--template`.
+    #[value(skip)]
+    Template,
+    /// TOML array of tables, named by `--record-name`.
+    Toml,
+    /// Tab-separated values, with a header and a column per field.
+    Tsv,
+    /// XML document, with an element per record.
+    Xml,
+    /// YAML sequence of records.
+    Yaml,
+}
+
+/// Options to choose how the generated data is written.
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    // The placeholders aren't spelled out here: clap turns `{n}` into a line
+    // break in the help.
+    /// String to print after every output generation, with placeholders for
+    /// the index of the record and the number of records (see USAGE).
+    #[arg(short, long, group = "combinable", default_value = "\n")]
+    after: String,
+    /// String to print after the last output generation, instead of
+    /// `--after`. [default: `--after`]
+    #[arg(long, group = "combinable", conflicts_with_all = ["duration", "forever", "size"])]
+    after_last: Option<String>,
+    /// Separator between the elements of arrays in csv and tsv, with
+    /// `--arrays join`.
+    #[arg(long, default_value = "|", group = "combinable")]
+    array_separator: String,
+    /// How to write arrays in csv and tsv.
+    #[arg(long, value_enum, default_value_t, group = "combinable")]
+    arrays: ArrayPolicy,
+    /// Number of records inserted by a single SQL statement, or sent by a
+    /// single request with `--post`.
+    #[arg(long, default_value = "1", group = "combinable")]
+    batch_size: NonZeroU64,
+    /// String to print before every output generation, with the same
+    /// placeholders as `--after`.
+    #[arg(short, long, group = "combinable", default_value = "")]
+    before: String,
+    /// Compress the output. Inferred from the extension of `--output`, `.gz`
+    /// or `.zst`, if not given.
+    #[arg(long, value_enum, group = "combinable")]
+    compress: Option<Compression>,
+    /// Number of requests sent at the same time with `--post`.
+    #[arg(long, default_value = "1", group = "combinable", requires = "post")]
+    concurrency: NonZeroUsize,
+    /// SQL dialect, to quote identifiers and escape literals.
+    #[arg(long, value_enum, default_value_t, group = "combinable")]
+    dialect: Dialect,
+    /// Format of the output. `--before` and `--after` are only used by the
+    /// json format. [default: json, or the format of csv and tsv patterns]
+    #[arg(long, value_enum, group = "combinable")]
+    format: Option<Format>,
+    /// How to separate the records in msgpack and cbor.
+    #[arg(long, value_enum, default_value_t, group = "combinable")]
+    framing: Framing,
+    /// Header of the requests sent with `--post`, with the format 'Name:
+    /// value'. Can be given several times.
+    #[arg(short = 'H', long = "header", group = "combinable", requires = "post")]
+    headers: Vec<String>,
+    /// Field of the records used as the `_id` of the documents, for the
+    /// es-bulk format.
+    #[arg(long, group = "combinable")]
+    id_field: Option<String>,
+    /// Number of spaces used to indent pretty-printed JSON. Use 0 for compact
+    /// JSON.
+    #[arg(long, default_value_t = 2, group = "combinable")]
+    indent: usize,
+    /// Name of the index in which the documents are indexed, for the es-bulk
+    /// format.
+    #[arg(long, group = "combinable")]
+    index: Option<String>,
+    /// String to write in csv and tsv for omitted fields, e.g. 'NULL'.
+    #[arg(long, default_value = "", group = "combinable")]
+    null: String,
+    /// Write the output to the given file instead of the standard output.
+    /// With placeholders, e.g. `users/{id}.json`, every record is written to
+    /// its own file: `{i}` is replaced by the index of the record and
+    /// `{field}` by the value of a field.
+    #[arg(short, long, group = "combinable")]
+    output: Option<PathBuf>,
+    /// Send the records as JSON to the given URL with POST requests, one
+    /// record per request, or arrays of `--batch-size` records.
+    #[arg(
+        long,
+        group = "combinable",
+        conflicts_with_all = ["format", "output", "sqlite", "template"]
+    )]
+    post: Option<String>,
+    /// Name of the element of every record in xml, and of the array of tables
+    /// in toml.
+    #[arg(long, default_value = "record", group = "combinable")]
+    record_name: String,
+    /// Number of times a request sent with `--post` is retried after a
+    /// server error (5xx) or a connection error.
+    #[arg(long, default_value_t = 3, group = "combinable", requires = "post")]
+    retries: u32,
+    /// Name of the root element in xml.
+    #[arg(long, default_value = "records", group = "combinable")]
+    root_name: String,
+    /// Split the output into files of the given number of records, numbered
+    /// after the `--output` file, e.g. `out-00000.json`.
+    #[arg(long, group = "combinable", requires = "output")]
+    split_every: Option<NonZeroU64>,
+    /// Write the records directly into the given `SQLite` database, in the
+    /// table given by `--table`.
+    #[arg(long, group = "combinable", conflicts_with = "format")]
+    sqlite: Option<PathBuf>,
+    /// Name of the table in which the records are inserted, for the sql
+    /// format and `--sqlite`.
+    #[arg(long, group = "combinable")]
+    table: Option<String>,
+    /// Render every record with the given Jinja template, instead of using a
+    /// format.
+    #[arg(long, group = "combinable", conflicts_with = "format")]
+    template: Option<PathBuf>,
+    /// How to write the fields of objects in xml.
+    #[arg(long, value_enum, default_value_t, group = "combinable")]
+    xml_fields: XmlFields,
+}
+
+/// Position of a record in the generated output.
+#[derive(Clone, Copy, Debug)]
+pub struct Position {
+    /// Number of the file in which the record is written, with
+    /// `--split-every`.
+    pub chunk: u64,
+    /// Number of records of the whole generation, given by `--count`, even
+    /// when only a shard of them is generated.
+    pub count: u64,
+    /// Index of the record, from the first record generated with the seed.
+    pub index: u64,
+    /// Whether this is the last record written by this run, or in the
+    /// current file if the output is split.
+    pub last: bool,
+    /// Number of records written by this run, or in the current file if the
+    /// output is split, before this one.
+    pub written: u64,
+}
+
+/// Writes the generated records in the requested format.
+pub struct Output {
+    /// Options given by the user.
+    args: OutputArgs,
+    /// Columns of the tabular formats, computed from the pattern.
+    columns: Vec<Column>,
+    /// Indentation of pretty-printed JSON, or [`None`] to write compact JSON.
+    indent: Option<String>,
+    /// Pattern of the records, to know the data types of the mongo format.
+    pattern: Value,
+    /// Template of the records, loaded from `--template`.
+    template: Option<Template>,
+}
+
+impl Output {
+    /// Settings of the es-bulk format.
+    fn bulk(&self) -> Res<Bulk<'_>> {
+        Ok(Bulk {
+            id_field: self.args.id_field.as_deref(),
+            index: self.args.index.as_deref().context(
+                "An index name is required for the es-bulk format, give it with `--index`",
+            )?,
+        })
+    }
+
+    /// Checks that the output can be written with the given limit.
+    ///
+    /// With `--size`, `--duration` or `--forever`, the number of records and
+    /// the last record aren't known in advance, so `{n}` and the
+    /// `record_last` variable of templates are rejected before anything is
+    /// written.
+    pub fn check_limit(&self, limit: Limit) -> Res {
+        match limit {
+            Limit::Count(_) => return Ok(()),
+            Limit::Duration(_) | Limit::Forever | Limit::Size(_) => (),
+        }
+        if self.args.before.contains("{n}") || self.args.after.contains("{n}") {
+            bail!(
+                "`{{n}}` can't be used with `--size`, `--duration` or `--forever`, as the number \
+                 of records isn't known in advance"
+            );
+        }
+        if let Some(path) = &self.args.template
+            && Template::load(path)?.uses("record_last")?
+        {
+            bail!(
+                "Templates can't use `record_last` with `--size`, `--duration` or `--forever`, as the \
+                 last record isn't known in advance"
+            );
+        }
+        Ok(())
+    }
+
+    /// Number of records written in every file, if the output is split.
+    ///
+    /// Every record is written in its own file if `--output` contains
+    /// placeholders.
+    pub fn chunk_size(&self) -> Option<NonZeroU64> {
+        match &self.args.output {
+            Some(path) if destination::is_template(path) => Some(NonZeroU64::MIN),
+            Some(_) | None => self.args.split_every,
+        }
+    }
+
+    /// Prepares the columnar file in which the records of the given pattern
+    /// are written, if the format is columnar.
+    pub fn columnar(&self, pattern: &Value) -> Res<Option<Columnar>> {
+        match self.format() {
+            Format::Arrow => Columnar::arrow(pattern).map(Some),
+            Format::Parquet => Columnar::parquet(pattern).map(Some),
+            Format::Cbor
+            | Format::Csv
+            | Format::EsBulk
+            | Format::Json
+            | Format::JsonArray
+            | Format::Mongo
+            | Format::Msgpack
+            | Format::Ndjson
+            | Format::PgCopy
+            | Format::PgCopyBinary
+            | Format::Sql
+            | Format::Template
+            | Format::Toml
+            | Format::Tsv
+            | Format::Xml
+            | Format::Yaml => Ok(None),
+        }
+    }
+
+    /// Compression of the output, inferred from the extension of `--output`
+    /// if not given.
+    fn compression(&self) -> Option<Compression> {
+        self.args
+            .compress
+            .or_else(|| self.args.output.as_deref().and_then(Compression::infer))
+    }
+
+    /// Opens the database given with `--sqlite`, if any, to insert the
+    /// records of the given pattern.
+    pub fn database(&self, pattern: &Value) -> Res<Option<Database>> {
+        self.args
+            .sqlite
+            .as_ref()
+            .map(|path| Database::create(path, self.table_name()?, columns(pattern, false)))
+            .transpose()
+    }
+
+    /// Opens the destination of the records, that is `writer` unless
+    /// `--output` is given.
+    pub fn destination<'writer, W: Write>(
+        &self,
+        writer: &'writer mut W,
+    ) -> Res<Destination<'writer, W>> {
+        let mut destination = Destination::new(
+            writer,
+            self.compression(),
+            self.args.output.is_some(),
+            self.args
+                .output
+                .as_deref()
+                .is_some_and(destination::is_template),
+        )?;
+        if let Some(path) = self.single_path() {
+            destination.open(path)?;
+        }
+        Ok(destination)
+    }
+
+    /// Starts sending requests to the URL given with `--post`, if any.
+    pub fn endpoint(&self) -> Res<Option<Endpoint>> {
+        self.args
+            .post
+            .as_deref()
+            .map(|url| {
+                Endpoint::new(
+                    url,
+                    &self.args.headers,
+                    self.args.batch_size,
+                    self.args.concurrency,
+                    self.args.retries,
+                )
+            })
+            .transpose()
+    }
+
+    /// Format of the output.
+    fn format(&self) -> Format {
+        self.args.format.unwrap_or_default()
+    }
+
+    /// Settings of the SQL format.
+    fn insert(&self) -> Res<Insert<'_>> {
+        Ok(Insert {
+            batch_size: self.args.batch_size.get(),
+            dialect: self.args.dialect,
+            table: self.table_name()?,
+        })
+    }
+
+    /// Path of the file to open before writing the record at `position`, if
+    /// it is the first record of a file of a split output.
+    pub fn path(&self, value: &Value, position: Position) -> Res<Option<PathBuf>> {
+        if position.written != 0 {
+            return Ok(None);
+        }
+        match (&self.args.output, self.args.split_every) {
+            (Some(path), _) if destination::is_template(path) =>
+                destination::record_path(path, value, position).map(Some),
+            (Some(path), Some(_)) => Ok(Some(destination::chunk_path(path, position.chunk))),
+            (Some(_) | None, _) => Ok(None),
+        }
+    }
+
+    /// Prepares the output for the records of the given pattern.
+    pub fn prepare(&mut self, pattern: &Value) -> Res {
+        match self.format() {
+            Format::Csv | Format::Tsv => self.columns = columns(pattern, true),
+            Format::PgCopy => self.columns = columns(pattern, false),
+            Format::PgCopyBinary => {
+                self.columns = columns(pattern, false);
+                copy::check_bigints(&self.columns)?;
+            }
+            Format::Sql => {
+                self.insert()?;
+                self.columns = columns(pattern, false);
+            }
+            Format::EsBulk => self.bulk().map(drop)?,
+            Format::Mongo => pattern.clone_into(&mut self.pattern),
+            Format::Template => {
+                let path = self
+                    .args
+                    .template
+                    .as_ref()
+                    .context("A template is required, give it with `--template`")?;
+                self.template = Some(Template::load(path)?);
+            }
+            Format::Arrow
+            | Format::Cbor
+            | Format::Json
+            | Format::JsonArray
+            | Format::Msgpack
+            | Format::Ndjson
+            | Format::Parquet
+            | Format::Toml
+            | Format::Xml
+            | Format::Yaml => (),
+        }
+        Ok(())
+    }
+
+    /// Sets the format of the output, if it wasn't chosen by the user.
+    pub const fn set_default_format(&mut self, format: Format) {
+        if self.args.format.is_none() {
+            self.args.format = Some(format);
+        }
+    }
+
+    /// File in which all the records are written, if the output isn't split.
+    fn single_path(&self) -> Option<&Path> {
+        self.args
+            .output
+            .as_deref()
+            .filter(|_| self.chunk_size().is_none())
+    }
+
+    /// Settings of the tabular formats.
+    fn table(&self) -> Table<'_> {
+        Table {
+            arrays: self.args.arrays,
+            array_separator: &self.args.array_separator,
+            delimiter: if self.format() == Format::Tsv {
+                '\t'
+            } else {
+                ','
+            },
+            null: &self.args.null,
+        }
+    }
+
+    /// Name of the table in which the records are inserted.
+    fn table_name(&self) -> Res<&str> {
+        self.args
+            .table
+            .as_deref()
+            .context("A table name is required to insert records, give it with `--table`")
+    }
+
+    /// Template loaded by [`Self::write_header`].
+    fn template(&self) -> Res<&Template> {
+        self.template
+            .as_ref()
+            .context("The template must be loaded before writing records")
+    }
+
+    /// Writes what must be written after the last record, when `written`
+    /// records were written in the current file.
+    pub fn write_footer<W: Write>(&self, writer: &mut W, written: u64) -> Res {
+        match self.format() {
+            Format::JsonArray if self.indent.is_some() => write_str(writer, "\n]\n"),
+            Format::JsonArray => write_str(writer, "]\n"),
+            Format::PgCopy if self.args.table.is_some() => write_str(writer, "\\.\n"),
+            Format::PgCopyBinary => write_str(writer, copy::BINARY_TRAILER),
+            Format::Xml => write_str(writer, self.xml().footer()),
+            Format::Template => write_str(writer, self.template()?.footer()?),
+            Format::Sql => write_str(writer, self.insert()?.footer(written)),
+            Format::Arrow
+            | Format::Cbor
+            | Format::Csv
+            | Format::EsBulk
+            | Format::Json
+            | Format::Mongo
+            | Format::Msgpack
+            | Format::Ndjson
+            | Format::Parquet
+            | Format::PgCopy
+            | Format::Toml
+            | Format::Tsv
+            | Format::Yaml => Ok(()),
+        }
+    }
+
+    /// Writes what must be written before the first record.
+    pub fn write_header<W: Write>(&self, writer: &mut W) -> Res {
+        match self.format() {
+            Format::JsonArray => write_str(writer, "["),
+            Format::Csv | Format::Tsv => write_str(writer, self.table().header(&self.columns)),
+            Format::PgCopy =>
+                self.args.table.as_ref().map_or(Ok(()), |table| {
+                    write_str(writer, copy::text_header(table, &self.columns))
+                }),
+            Format::PgCopyBinary => write_str(writer, copy::BINARY_HEADER),
+            Format::Xml => write_str(writer, self.xml().header()),
+            Format::Template => write_str(writer, self.template()?.header()?),
+            Format::Arrow
+            | Format::Cbor
+            | Format::EsBulk
+            | Format::Json
+            | Format::Mongo
+            | Format::Msgpack
+            | Format::Ndjson
+            | Format::Parquet
+            | Format::Sql
+            | Format::Toml
+            | Format::Yaml => Ok(()),
+        }
+    }
+
+    /// Writes a JSON value, compact or pretty-printed with the configured
+    /// indentation.
+    fn write_json<W: Write>(&self, value: &Value, writer: &mut W) -> Res {
+        if let Some(indent) = &self.indent {
+            let formatter = PrettyFormatter::with_indent(indent.as_bytes());
+            value.serialize(&mut Serializer::with_formatter(writer, formatter))
+        } else {
+            value.serialize(&mut Serializer::with_formatter(writer, CompactFormatter))
+        }
+        .context("Failed to serialise json")
+    }
+
+    /// Writes a generated record.
+    pub fn write_record<W: Write>(&self, value: &Value, position: Position, writer: &mut W) -> Res {
+        match self.format() {
+            Format::Csv | Format::Tsv => write_str(writer, self.table().rows(&self.columns, value)),
+            Format::Sql => write_str(writer, self.insert()?.record(&self.columns, value, position)),
+            Format::PgCopy => write_str(writer, copy::text_row(&self.columns, value)),
+            Format::PgCopyBinary => write_str(writer, copy::binary_row(&self.columns, value)?),
+            Format::Arrow | Format::Parquet =>
+                bail!("Columnar formats are written by row groups, with `Output::columnar`"),
+            Format::Json => {
+                write_str(writer, placeholders(&self.args.before, position))?;
+                self.write_json(value, writer)?;
+                let after = match &self.args.after_last {
+                    Some(after_last) if position.last => after_last,
+                    Some(_) | None => &self.args.after,
+                };
+                write_str(writer, placeholders(after, position))
+            }
+            Format::EsBulk => write_str(writer, self.bulk()?.record(value)?),
+            Format::Toml => write_str(writer, documents::toml(&self.args.record_name, value)?),
+            Format::Xml => write_str(writer, self.xml().record(value)),
+            Format::Template => write_str(writer, self.template()?.record(value, position)?),
+            Format::Yaml => write_str(writer, documents::yaml(value)?),
+            Format::Cbor => write_str(writer, self.args.framing.frame(binary::cbor(value)?)?),
+            Format::Msgpack => write_str(writer, self.args.framing.frame(binary::msgpack(value)?)?),
+            Format::Mongo => {
+                let mut document = value.clone();
+                mongo::extend(&self.pattern, false, &mut document);
+                serde_json::to_writer(&mut *writer, &document)
+                    .context("Failed to serialise json")?;
+                write_str(writer, "\n")
+            }
+            Format::Ndjson => {
+                serde_json::to_writer(&mut *writer, value).context("Failed to serialise json")?;
+                write_str(writer, "\n")
+            }
+            Format::JsonArray => {
+                if position.written != 0 {
+                    write_str(writer, ",")?;
+                }
+                if let Some(indent) = &self.indent {
+                    let mut buffer = vec![];
+                    self.write_json(value, &mut buffer)?;
+                    let record = String::from_utf8(buffer).context("Failed to serialise json")?;
+                    write_str(writer, "\n")?;
+                    write_str(writer, indent)?;
+                    write_str(writer, record.replace('\n', &format!("\n{indent}")))
+                } else {
+                    self.write_json(value, writer)
+                }
+            }
+        }
+    }
+
+    /// Settings of the xml format.
+    fn xml(&self) -> Xml<'_> {
+        Xml {
+            fields: self.args.xml_fields,
+            indent: self.indent.as_deref(),
+            record: &self.args.record_name,
+            root: &self.args.root_name,
+        }
+    }
+}
+
+impl From<OutputArgs> for Output {
+    fn from(mut args: OutputArgs) -> Self {
+        if args.template.is_some() {
+            args.format = Some(Format::Template);
+        }
+        Self {
+            columns: vec![],
+            indent: (args.indent != 0).then(|| " ".repeat(args.indent)),
+            pattern: Value::Null,
+            template: None,
+            args,
+        }
+    }
+}
+
+/// Replaces the placeholders of `--before` and `--after` with the values of
+/// the record at `position`.
+fn placeholders(text: &str, position: Position) -> String {
+    text.replace("{i}", &position.index.to_string())
+        .replace("{n}", &position.count.to_string())
+}
+
+/// Writes a string or bytes to the output.
+fn write_str<W: Write, S: AsRef<[u8]>>(writer: &mut W, content: S) -> Res {
+    writer
+        .write_all(content.as_ref())
+        .context("Failed to write output")
+}
@@ -0,0 +1,58 @@
+//! Write the generated records as `MongoDB` Extended JSON, to be imported with
+//! `mongoimport`.
+
+use core::mem;
+
+use serde_json::{Value, json};
+
+use crate::data::{OutputData, OutputKind};
+use crate::output::flatten::parse_key;
+
+/// Converts the values of a generated record to Extended JSON, with the data
+/// types of the pattern.
+///
+/// `ObjectId` values are written as `{"$oid": ...}`, `Date` values as
+/// `{"$date": ...}` and integers as `{"$numberLong": ...}`, or as
+/// `{"$numberDecimal": ...}` if they are too large for a 64-bit integer.
+/// `literal` is `true` if the value was marked with `!`: it is then left
+/// intact.
+pub fn extend(pattern: &Value, literal: bool, value: &mut Value) {
+    if literal {
+        return;
+    }
+    match (pattern, value) {
+        (Value::Object(pattern_object), Value::Object(object)) =>
+            for (key, child_pattern) in pattern_object {
+                let (name, child_literal, _) = parse_key(key, false, false);
+                if let Some(child) = object.get_mut(name) {
+                    extend(child_pattern, child_literal, child);
+                }
+            },
+        (Value::Array(pattern_items), Value::Array(items)) =>
+            if let Some(item_pattern) = pattern_items.first() {
+                for item in items {
+                    extend(item_pattern, false, item);
+                }
+            },
+        (Value::String(data_type), generated @ (Value::String(_) | Value::Number(_))) => {
+            let wrapper = match (OutputData::type_name(data_type), &*generated) {
+                ("ObjectId", _) => "$oid",
+                ("Date", _) => "$date",
+                (_, Value::Number(number)) if OutputData::kind(data_type) == OutputKind::Int =>
+                    if number.is_i64() {
+                        "$numberLong"
+                    } else {
+                        "$numberDecimal"
+                    },
+                _ => return,
+            };
+            let inner = if let Value::Number(number) = generated {
+                Value::String(number.to_string())
+            } else {
+                mem::take(generated)
+            };
+            *generated = json!({ wrapper: inner });
+        }
+        _ => (),
+    }
+}
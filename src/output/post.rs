@@ -0,0 +1,236 @@
+//! Send the generated records to an HTTP endpoint with `POST` requests.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use core::iter::repeat_with;
+use core::mem;
+use core::num::{NonZeroU64, NonZeroUsize};
+use core::time::Duration;
+use std::io::Write;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+
+use color_eyre::eyre::{Context as _, ContextCompat as _, bail, eyre};
+use ureq::{Agent, AgentBuilder, Error};
+
+use crate::Res;
+
+/// Delay before the first retry of a failed request, doubled for every
+/// following retry.
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Time after which a request is considered as failed.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Body of a request, with the number of records it contains.
+struct Batch {
+    /// JSON record, or JSON array of records.
+    body: String,
+    /// Number of records of the body.
+    records: u64,
+}
+
+/// Requests that failed by reason, with their number and the number of
+/// records they contained.
+type Failures = BTreeMap<String, (u64, u64)>;
+
+/// Settings of the requests, shared by the threads sending them.
+struct Client {
+    /// Agent sending the requests, reusing the connections.
+    agent: Agent,
+    /// Headers of every request.
+    headers: Vec<(String, String)>,
+    /// Number of times a request is retried after a server or connection
+    /// error.
+    retries: u32,
+    /// URL to which the requests are sent.
+    url: String,
+}
+
+impl Client {
+    /// Sends a request, and returns the reason of its failure, if it failed
+    /// even after the retries.
+    ///
+    /// Client errors (4xx) aren't retried, as they would fail again.
+    fn send(&self, body: &str) -> Result<(), String> {
+        let mut attempt = 0;
+        loop {
+            let request = self
+                .headers
+                .iter()
+                .fold(self.agent.post(&self.url), |request, (name, value)| {
+                    request.set(name, value)
+                });
+            let reason = match request.send_string(body) {
+                Ok(response) => {
+                    // The response is read so that the connection is reused.
+                    drop(response.into_string());
+                    return Ok(());
+                }
+                Err(Error::Status(code, response)) if code < 500 =>
+                    return Err(format!("{code} {}", response.status_text())),
+                Err(Error::Status(code, response)) => format!("{code} {}", response.status_text()),
+                Err(Error::Transport(transport)) => transport.to_string(),
+            };
+            if attempt >= self.retries {
+                return Err(reason);
+            }
+            thread::sleep(RETRY_DELAY.saturating_mul(2u32.saturating_pow(attempt)));
+            attempt = attempt.saturating_add(1);
+        }
+    }
+
+    /// Sends the batches received on `receiver` until all of them are sent,
+    /// and returns the requests that failed.
+    fn work(&self, receiver: &Mutex<Receiver<Batch>>) -> Failures {
+        let mut failures = Failures::new();
+        while let Some(batch) = receiver.lock().ok().and_then(|guard| guard.recv().ok()) {
+            if let Err(reason) = self.send(&batch.body) {
+                let failure = failures.entry(reason).or_default();
+                failure.0 = failure.0.saturating_add(1);
+                failure.1 = failure.1.saturating_add(batch.records);
+            }
+        }
+        failures
+    }
+}
+
+/// HTTP endpoint to which the records are sent, by batches, from several
+/// threads.
+pub struct Endpoint {
+    /// Records of the next request.
+    batch: Vec<String>,
+    /// Number of records sent by a single request.
+    batch_size: usize,
+    /// Number of records sent so far.
+    records: u64,
+    /// Number of requests sent so far.
+    requests: u64,
+    /// Channel of the requests to send, read by the threads.
+    sender: SyncSender<Batch>,
+    /// URL to which the requests are sent.
+    url: String,
+    /// Threads sending the requests, returning the ones that failed.
+    workers: Vec<JoinHandle<Failures>>,
+}
+
+impl Endpoint {
+    /// Sends the remaining records, waits for all the requests to complete,
+    /// and writes a summary to `writer`.
+    ///
+    /// Fails with the reasons of the failures if any request failed.
+    pub fn finish<W: Write>(mut self, writer: &mut W) -> Res {
+        self.flush()?;
+        let Self { records, requests, sender, url, workers, .. } = self;
+        drop(sender);
+        let mut failures = Failures::new();
+        for worker in workers {
+            let worker_failures = worker
+                .join()
+                .map_err(|_panic| eyre!("A thread sending requests panicked"))?;
+            for (reason, (count, lost)) in worker_failures {
+                let failure = failures.entry(reason).or_default();
+                failure.0 = failure.0.saturating_add(count);
+                failure.1 = failure.1.saturating_add(lost);
+            }
+        }
+        let failed = failures.values().map(|&(count, _)| count).sum::<u64>();
+        let lost = failures.values().map(|&(_, lost)| lost).sum::<u64>();
+        writeln!(
+            writer,
+            "Sent {} of {records} records to {url} in {requests} requests",
+            records.saturating_sub(lost)
+        )
+        .context("Failed to write output")?;
+        if failed > 0 {
+            let reasons = failures
+                .iter()
+                .map(|(reason, &(count, _))| format!("{reason} ({count})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            bail!("{failed} of {requests} requests failed, losing {lost} records: {reasons}");
+        }
+        Ok(())
+    }
+
+    /// Queues a request with the pending records, if any.
+    ///
+    /// With a batch size of 1, the body is the record itself, and otherwise
+    /// an array of records.
+    fn flush(&mut self) -> Res {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        let records = mem::take(&mut self.batch);
+        let count = u64::try_from(records.len()).unwrap_or(u64::MAX);
+        let body = if self.batch_size == 1 {
+            records.concat()
+        } else {
+            format!("[{}]", records.join(","))
+        };
+        self.records = self.records.saturating_add(count);
+        self.requests = self.requests.saturating_add(1);
+        self.sender
+            .send(Batch { body, records: count })
+            .map_err(|_batch| eyre!("The threads sending requests stopped"))
+    }
+
+    /// Starts `concurrency` threads sending requests to `url`, with the given
+    /// headers, each of the format 'Name: value'.
+    pub fn new(
+        url: &str,
+        headers: &[String],
+        batch_size: NonZeroU64,
+        concurrency: NonZeroUsize,
+        retries: u32,
+    ) -> Res<Self> {
+        let mut parsed = vec![];
+        for header in headers {
+            let (name, value) = header
+                .split_once(':')
+                .with_context(|| format!("Invalid header '{header}', expected 'Name: value'"))?;
+            parsed.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
+        if !parsed
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+        {
+            parsed.push(("Content-Type".to_owned(), "application/json".to_owned()));
+        }
+        let agent = AgentBuilder::new().timeout(TIMEOUT).build();
+        agent
+            .post(url)
+            .request_url()
+            .with_context(|| format!("Invalid URL {url}"))?;
+        let client = Arc::new(Client { agent, headers: parsed, retries, url: url.to_owned() });
+        let (sender, receiver) = mpsc::sync_channel(concurrency.get());
+        let shared_receiver = Arc::new(Mutex::new(receiver));
+        let workers = repeat_with(|| {
+            let worker_client = Arc::clone(&client);
+            let worker_receiver = Arc::clone(&shared_receiver);
+            thread::spawn(move || worker_client.work(&worker_receiver))
+        })
+        .take(concurrency.get())
+        .collect();
+        Ok(Self {
+            batch: vec![],
+            batch_size: usize::try_from(batch_size.get()).unwrap_or(usize::MAX),
+            records: 0,
+            requests: 0,
+            sender,
+            url: url.to_owned(),
+            workers,
+        })
+    }
+
+    /// Adds a JSON record to the next request, and queues the request if it
+    /// is full.
+    pub fn push(&mut self, record: String) -> Res {
+        self.batch.push(record);
+        if self.batch.len() >= self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Write the generated records as SQL `INSERT` statements.
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::output::Position;
+use crate::output::flatten::Column;
+
+/// SQL dialect, to quote identifiers and escape literals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Dialect {
+    /// `MySQL` and `MariaDB`
+    Mysql,
+    /// `PostgreSQL`
+    #[default]
+    Postgres,
+    /// `SQLite`
+    Sqlite,
+}
+
+/// Settings to write records as SQL `INSERT` statements.
+pub struct Insert<'args> {
+    /// Number of records inserted by a single statement.
+    pub batch_size: u64,
+    /// SQL dialect.
+    pub dialect: Dialect,
+    /// Name of the table in which the records are inserted.
+    pub table: &'args str,
+}
+
+impl Dialect {
+    /// Quotes an identifier, like a table or a column name.
+    pub fn identifier(self, name: &str) -> String {
+        match self {
+            Self::Mysql => format!("`{}`", name.replace('`', "``")),
+            Self::Postgres | Self::Sqlite => format!("\"{}\"", name.replace('"', "\"\"")),
+        }
+    }
+}
+
+impl Insert<'_> {
+    /// Closes the statement of the last batch, if it wasn't full, after
+    /// `written` records.
+    pub fn footer(&self, written: u64) -> &'static str {
+        if written.checked_rem(self.batch_size).unwrap_or_default() == 0 {
+            ""
+        } else {
+            ";\n"
+        }
+    }
+
+    /// Writes a value as an SQL literal.
+    ///
+    /// Omitted fields are `NULL`, and nested objects and arrays are written
+    /// as JSON.
+    fn literal(&self, value: Option<&Value>) -> String {
+        match value {
+            None | Some(Value::Null) => "NULL".to_owned(),
+            Some(Value::Bool(bool)) if self.dialect == Dialect::Sqlite =>
+                u8::from(*bool).to_string(),
+            Some(Value::Bool(true)) => "TRUE".to_owned(),
+            Some(Value::Bool(false)) => "FALSE".to_owned(),
+            Some(Value::Number(number)) => number.to_string(),
+            Some(Value::String(string)) => self.string(string),
+            Some(json @ (Value::Array(_) | Value::Object(_))) => match self.dialect {
+                Dialect::Postgres => format!("{}::jsonb", self.string(&json.to_string())),
+                Dialect::Mysql => format!("CAST({} AS JSON)", self.string(&json.to_string())),
+                Dialect::Sqlite => self.string(&json.to_string()),
+            },
+        }
+    }
+
+    /// Formats the part of the `INSERT` statement for a generated record.
+    ///
+    /// A new statement is started every [`Self::batch_size`] records, and the
+    /// statement is closed after the last record of the batch, or by
+    /// [`Self::footer`] if the batch isn't full.
+    pub fn record(&self, columns: &[Column], record: &Value, position: Position) -> String {
+        let batch_position = position
+            .written
+            .checked_rem(self.batch_size)
+            .unwrap_or_default();
+        let mut statement = if batch_position == 0 {
+            format!(
+                "INSERT INTO {} ({}) VALUES\n  (",
+                self.dialect.identifier(self.table),
+                columns
+                    .iter()
+                    .map(|column| self.dialect.identifier(&column.name()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        } else {
+            ",\n  (".to_owned()
+        };
+        statement.push_str(
+            &columns
+                .iter()
+                .map(|column| self.literal(column.get(record)))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        statement.push(')');
+        if batch_position.saturating_add(1) == self.batch_size {
+            statement.push_str(";\n");
+        }
+        statement
+    }
+
+    /// Quotes and escapes a string literal.
+    fn string(&self, string: &str) -> String {
+        let escaped = string.replace('\'', "''");
+        match self.dialect {
+            Dialect::Mysql => format!("'{}'", escaped.replace('\\', "\\\\")),
+            Dialect::Postgres | Dialect::Sqlite => format!("'{escaped}'"),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Write the generated records directly into a `SQLite` database.
+
+use std::path::Path;
+
+use color_eyre::eyre::Context as _;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{Connection, params_from_iter};
+use serde_json::Value;
+
+use crate::Res;
+use crate::data::OutputKind;
+use crate::output::flatten::{Column, ColumnKind};
+use crate::output::sql::Dialect;
+
+/// Number of rows inserted by a single transaction.
+const TRANSACTION_SIZE: u64 = 10_000;
+
+/// Table of a `SQLite` database in which the records are inserted.
+pub struct Database {
+    /// Columns of the table.
+    columns: Vec<Column>,
+    /// Connection to the database.
+    connection: Connection,
+    /// `INSERT` statement for a row.
+    insert: String,
+    /// Number of rows inserted in the current transaction.
+    pending: u64,
+}
+
+impl Database {
+    /// Commits the rows that were inserted since the last commit.
+    pub fn commit(self) -> Res {
+        self.connection
+            .execute_batch("COMMIT;")
+            .context("Failed to commit the generated rows")
+    }
+
+    /// Opens the database and creates the table, if it doesn't exist.
+    ///
+    /// The types of the columns are inferred from the pattern.
+    pub fn create(path: &Path, table: &str, columns: Vec<Column>) -> Res<Self> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Failed to open database {}", path.display()))?;
+        let definitions = columns
+            .iter()
+            .map(|column| {
+                format!(
+                    "{} {}{}",
+                    Dialect::Sqlite.identifier(&column.name()),
+                    column_type(column.kind),
+                    if column.nullable { "" } else { " NOT NULL" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        connection
+            .execute_batch(&format!(
+                "CREATE TABLE IF NOT EXISTS {} ({definitions}); BEGIN;",
+                Dialect::Sqlite.identifier(table)
+            ))
+            .with_context(|| format!("Failed to create table {table}"))?;
+        let insert = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            Dialect::Sqlite.identifier(table),
+            columns
+                .iter()
+                .map(|column| Dialect::Sqlite.identifier(&column.name()))
+                .collect::<Vec<_>>()
+                .join(", "),
+            vec!["?"; columns.len()].join(", ")
+        );
+        Ok(Self { columns, connection, insert, pending: 0 })
+    }
+
+    /// Inserts a generated record in the table.
+    pub fn insert(&mut self, record: &Value) -> Res {
+        self.connection
+            .prepare_cached(&self.insert)
+            .context("Failed to prepare the insert statement")?
+            .execute(params_from_iter(
+                self.columns
+                    .iter()
+                    .map(|column| sql_value(column.get(record))),
+            ))
+            .context("Failed to insert a generated row")?;
+        self.pending = self.pending.saturating_add(1);
+        if self.pending == TRANSACTION_SIZE {
+            self.pending = 0;
+            self.connection
+                .execute_batch("COMMIT; BEGIN;")
+                .context("Failed to commit the generated rows")?;
+        }
+        Ok(())
+    }
+}
+
+/// Type of a column, from the kind of its values.
+///
+/// Nested objects and arrays are stored as JSON text.
+const fn column_type(kind: ColumnKind) -> &'static str {
+    match kind {
+        ColumnKind::Data(OutputKind::Bool) => "BOOLEAN",
+        ColumnKind::Data(OutputKind::Float) => "REAL",
+        ColumnKind::Data(OutputKind::Int) => "INTEGER",
+        ColumnKind::Data(OutputKind::String) | ColumnKind::Json => "TEXT",
+    }
+}
+
+/// Converts a generated value to a `SQLite` value.
+///
+/// Integers that are too large for `SQLite` are stored as floats.
+fn sql_value(value: Option<&Value>) -> SqlValue {
+    match value {
+        None | Some(Value::Null) => SqlValue::Null,
+        Some(Value::Bool(bool)) => SqlValue::Integer(i64::from(*bool)),
+        Some(Value::Number(number)) => number
+            .as_i64()
+            .map_or_else(|| SqlValue::Real(number.as_f64().unwrap_or_default()), SqlValue::Integer),
+        Some(Value::String(string)) => SqlValue::Text(string.to_owned()),
+        Some(json @ (Value::Array(_) | Value::Object(_))) => SqlValue::Text(json.to_string()),
+    }
+}
@@ -0,0 +1,96 @@
+//! Render the generated records with a Jinja template.
+
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::Context as _;
+use minijinja::{AutoEscape, Environment, ErrorKind, context};
+use serde_json::Value;
+
+use crate::Res;
+use crate::output::Position;
+
+/// Name of the template in the environment.
+const NAME: &str = "template";
+
+/// Template rendered for every record.
+///
+/// If the template defines a `record` block, only its `header`, `record` and
+/// `footer` blocks are rendered. Otherwise, the whole template is rendered
+/// for every record.
+pub struct Template {
+    /// Environment containing the template.
+    environment: Environment<'static>,
+}
+
+impl Template {
+    /// Renders the `footer` block, if any.
+    pub fn footer(&self) -> Res<String> {
+        self.render_block("footer", context! {}, false)
+    }
+
+    /// Renders the `header` block, if any.
+    pub fn header(&self) -> Res<String> {
+        self.render_block("header", context! {}, false)
+    }
+
+    /// Reads and compiles the template file.
+    pub fn load(path: &Path) -> Res<Self> {
+        let source = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read template {}", path.display()))?;
+        let mut environment = Environment::new();
+        environment.set_auto_escape_callback(|_| AutoEscape::None);
+        environment.set_keep_trailing_newline(true);
+        environment
+            .add_template_owned(NAME, source)
+            .with_context(|| format!("Invalid template {}", path.display()))?;
+        Ok(Self { environment })
+    }
+
+    /// Renders the template for a generated record.
+    ///
+    /// The fields of the record are available directly, and the record
+    /// itself as `record`, with its index as `record_index` and whether it is
+    /// the first or last record written by this run as `record_first` and
+    /// `record_last`. The prefix keeps them apart from the fields.
+    pub fn record(&self, record: &Value, position: Position) -> Res<String> {
+        let ctx = context! {
+            record,
+            record_first => position.written == 0,
+            record_index => position.index,
+            record_last => position.last,
+            ..minijinja::Value::from_serialize(record)
+        };
+        self.render_block("record", ctx, true)
+    }
+
+    /// Renders a block of the template.
+    ///
+    /// If the template doesn't define this block, this returns the whole
+    /// rendered template if `whole` is `true`, and an empty string otherwise.
+    fn render_block(&self, block: &str, ctx: minijinja::Value, whole: bool) -> Res<String> {
+        let mut captured = self
+            .environment
+            .get_template(NAME)
+            .and_then(|template| template.render_captured(ctx))
+            .context("Failed to render template")?;
+        match captured.with_state_mut(|state| state.render_block(block)) {
+            Err(err) if err.kind() == ErrorKind::UnknownBlock => Ok(if whole {
+                captured.into_output()
+            } else {
+                String::new()
+            }),
+            rendered => rendered.with_context(|| format!("Failed to render block {block}")),
+        }
+    }
+
+    /// Whether the template uses the given variable.
+    pub fn uses(&self, variable: &str) -> Res<bool> {
+        Ok(self
+            .environment
+            .get_template(NAME)
+            .context("Failed to load template")?
+            .undeclared_variables(false)
+            .contains(variable))
+    }
+}
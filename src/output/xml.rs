@@ -0,0 +1,188 @@
+//! Write the generated records as XML, with an element per record.
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+/// How to write the fields of an object in XML.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum XmlFields {
+    /// Write the fields with a string, number or boolean value as attributes
+    /// of the element of the object, and the others as child elements.
+    Attributes,
+    /// Write all the fields as child elements.
+    #[default]
+    Elements,
+}
+
+/// Settings to write records as XML.
+pub struct Xml<'args> {
+    /// How to write the fields of objects.
+    pub fields: XmlFields,
+    /// Indentation of the elements, or [`None`] to write compact XML.
+    pub indent: Option<&'args str>,
+    /// Name of the element of every record.
+    pub record: &'args str,
+    /// Name of the element that contains all the records.
+    pub root: &'args str,
+}
+
+impl Xml<'_> {
+    /// Formats the element of a value, and pushes it to `out`.
+    ///
+    /// Arrays are written as a repeated element, one for each item.
+    fn element(&self, key: &str, value: &Value, depth: usize, out: &mut String) {
+        let name = &valid_name(key);
+        match value {
+            Value::Array(items) =>
+                for item in items {
+                    self.element(key, item, depth, out);
+                },
+            Value::Object(object) => {
+                self.start_line(depth, out);
+                out.push('<');
+                out.push_str(name);
+                let mut children = vec![];
+                for (child_key, child) in object {
+                    match child {
+                        Value::Bool(_) | Value::Number(_) | Value::String(_)
+                            if self.fields == XmlFields::Attributes =>
+                        {
+                            out.push(' ');
+                            out.push_str(&valid_name(child_key));
+                            out.push_str("=\"");
+                            out.push_str(&escape(&text(child)));
+                            out.push('"');
+                        }
+                        Value::Null
+                        | Value::Bool(_)
+                        | Value::Number(_)
+                        | Value::String(_)
+                        | Value::Array(_)
+                        | Value::Object(_) => children.push((child_key, child)),
+                    }
+                }
+                if children.is_empty() {
+                    out.push_str("/>");
+                } else {
+                    out.push('>');
+                    for (child_key, child) in children {
+                        self.element(child_key, child, depth.saturating_add(1), out);
+                    }
+                    self.start_line(depth, out);
+                    push_tag("</", name, ">", out);
+                }
+            }
+            Value::Null => {
+                self.start_line(depth, out);
+                push_tag("<", name, "/>", out);
+            }
+            Value::Bool(_) | Value::Number(_) | Value::String(_) => {
+                self.start_line(depth, out);
+                push_tag("<", name, ">", out);
+                out.push_str(&escape(&text(value)));
+                push_tag("</", name, ">", out);
+            }
+        }
+    }
+
+    /// Formats the end of the document.
+    pub fn footer(&self) -> String {
+        let mut footer = String::new();
+        self.start_line(0, &mut footer);
+        push_tag("</", &valid_name(self.root), ">\n", &mut footer);
+        footer
+    }
+
+    /// Formats the start of the document.
+    pub fn header(&self) -> String {
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<{}>", valid_name(self.root))
+    }
+
+    /// Formats the element of a generated record.
+    pub fn record(&self, record: &Value) -> String {
+        let mut out = String::new();
+        self.element(self.record, record, 1, &mut out);
+        out
+    }
+
+    /// Starts a new line with the indentation of `depth`, if the XML is
+    /// pretty-printed.
+    fn start_line(&self, depth: usize, out: &mut String) {
+        if let Some(indent) = self.indent {
+            out.push('\n');
+            out.push_str(&indent.repeat(depth));
+        }
+    }
+}
+
+/// Escapes the special characters of XML in a text or an attribute.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Whether a character can start an XML name, following the `NameStartChar`
+/// production, without `:` that is reserved for namespaces.
+const fn is_name_start(char: char) -> bool {
+    matches!(
+        char,
+        'A'..='Z'
+            | '_'
+            | 'a'..='z'
+            | '\u{C0}'..='\u{D6}'
+            | '\u{D8}'..='\u{F6}'
+            | '\u{F8}'..='\u{2FF}'
+            | '\u{370}'..='\u{37D}'
+            | '\u{37F}'..='\u{1FFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{2070}'..='\u{218F}'
+            | '\u{2C00}'..='\u{2FEF}'
+            | '\u{3001}'..='\u{D7FF}'
+            | '\u{F900}'..='\u{FDCF}'
+            | '\u{FDF0}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{EFFFF}'
+    )
+}
+
+/// Whether a character can be part of an XML name, following the `NameChar`
+/// production, without `:`.
+const fn is_name_char(char: char) -> bool {
+    is_name_start(char)
+        || matches!(
+            char,
+            '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}'
+        )
+}
+
+/// Pushes a tag to `out`, e.g. `</name>` with `start` `</` and `end` `>`.
+fn push_tag(start: &str, name: &str, end: &str, out: &mut String) {
+    out.push_str(start);
+    out.push_str(name);
+    out.push_str(end);
+}
+
+/// Makes a key a valid XML name, by replacing the invalid characters with
+/// `_`, and prefixing it with `_` if it can't start a name, e.g. `first name`
+/// becomes `first_name` and `1x` becomes `_1x`.
+fn valid_name(key: &str) -> String {
+    let mut name = key
+        .chars()
+        .map(|char| if is_name_char(char) { char } else { '_' })
+        .collect::<String>();
+    if !name.starts_with(is_name_start) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// Text of a value that isn't an object or an array.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.to_owned(),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::Array(_) | Value::Object(_) =>
+            value.to_string(),
+    }
+}
@@ -0,0 +1,142 @@
+//! Parse the patterns given by the user, in JSON, CSV or TSV.
+
+use core::mem;
+use std::path::Path;
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Context as _, bail};
+use serde_json::{Map, Value};
+
+use crate::Res;
+use crate::output::Format;
+
+/// Format of a pattern.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum PatternFormat {
+    /// Detect the format from the extension of the file or from the content
+    /// of the pattern.
+    #[default]
+    Auto,
+    /// A header row with the names of the columns, and a row with their data
+    /// types, separated by commas.
+    Csv,
+    /// A JSON value.
+    Json,
+    /// A header row with the names of the columns, and a row with their data
+    /// types, separated by tabs.
+    Tsv,
+}
+
+impl PatternFormat {
+    /// Detects the format of a pattern from its content.
+    fn detect(pattern: &str) -> Self {
+        let trimmed = pattern.trim_start();
+        if trimmed.starts_with(['{', '[', '"']) {
+            Self::Json
+        } else if trimmed
+            .lines()
+            .next()
+            .is_some_and(|line| line.contains('\t'))
+        {
+            Self::Tsv
+        } else {
+            Self::Csv
+        }
+    }
+
+    /// Detects the format of a pattern file from its extension.
+    ///
+    /// Returns [`Self::Auto`] if the extension is unknown.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Self::Csv,
+            Some("json") => Self::Json,
+            Some("tsv") => Self::Tsv,
+            _ => Self::Auto,
+        }
+    }
+
+    /// Parses a pattern into the JSON pattern used for the generation.
+    ///
+    /// CSV and TSV patterns are converted into an object, with a key per
+    /// column. Cells starting with `[` or `{` are parsed as JSON, to allow
+    /// arrays and nested objects.
+    ///
+    /// # Returns
+    ///
+    /// Returns the pattern, and the default output format for this pattern.
+    pub fn parse(self, pattern: &str) -> Res<(Value, Format)> {
+        match self {
+            Self::Auto => Self::detect(pattern).parse(pattern),
+            Self::Json => Ok((
+                serde_json::from_str(pattern).context("Failed to deserialise json")?,
+                Format::Json,
+            )),
+            Self::Csv => Ok((parse_table(pattern, ',')?, Format::Csv)),
+            Self::Tsv => Ok((parse_table(pattern, '\t')?, Format::Tsv)),
+        }
+    }
+}
+
+/// Parses a CSV or TSV pattern, with a header row and a row of data types.
+fn parse_table(pattern: &str, delimiter: char) -> Res<Value> {
+    let mut lines = pattern.lines().filter(|line| !line.trim().is_empty());
+    let (Some(header), Some(types)) = (lines.next(), lines.next()) else {
+        bail!("Tabular patterns must have a header row and a row with the data types")
+    };
+    if lines.next().is_some() {
+        bail!("Tabular patterns must only have a header row and a row with the data types")
+    }
+    let names = split_row(header, delimiter)?;
+    let data_types = split_row(types, delimiter)?;
+    if names.len() != data_types.len() {
+        bail!(
+            "Tabular pattern has {} columns but {} data types",
+            names.len(),
+            data_types.len()
+        )
+    }
+    let mut object = Map::with_capacity(names.len());
+    for (name, data_type) in names.into_iter().zip(data_types) {
+        let value = if data_type.starts_with(['[', '{']) {
+            serde_json::from_str(&data_type)
+                .with_context(|| format!("Failed to deserialise json of column {name}"))?
+        } else {
+            Value::String(data_type)
+        };
+        if object.insert(name.clone(), value).is_some() {
+            bail!("Column {name} was given twice")
+        }
+    }
+    Ok(Value::Object(object))
+}
+
+/// Splits a row of a CSV or TSV pattern into its cells.
+///
+/// In CSV, cells can be quoted with `"`, and quotes are escaped by doubling
+/// them.
+fn split_row(row: &str, delimiter: char) -> Res<Vec<String>> {
+    if delimiter == '\t' {
+        return Ok(row.split('\t').map(|cell| cell.trim().to_owned()).collect());
+    }
+    let mut cells = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted && chars.next_if_eq(&'"').is_some() => current.push('"'),
+            '"' => quoted = !quoted,
+            _ if ch == delimiter && !quoted => cells.push(mem::take(&mut current)),
+            _ => current.push(ch),
+        }
+    }
+    if quoted {
+        bail!("Unclosed quote in row `{row}`")
+    }
+    cells.push(current);
+    Ok(cells
+        .into_iter()
+        .map(|cell| cell.trim().to_owned())
+        .collect())
+}
@@ -0,0 +1,409 @@
+//! Serve generated records over HTTP, as a mock REST API.
+
+mod query;
+mod store;
+mod stream;
+
+use core::num::NonZeroU32;
+use core::str::FromStr;
+use core::time::Duration;
+use std::io::{Cursor, Write};
+use std::sync::Mutex;
+use std::{fs, thread};
+
+use clap::Args;
+use color_eyre::eyre::{Context as _, bail, eyre};
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::Res;
+use crate::clap::Rate;
+use crate::data::Data;
+use crate::json::{Pace, generate_record};
+use crate::pattern::PatternFormat;
+use crate::serve::query::Query;
+use crate::serve::store::{Store, set_id};
+use crate::serve::stream::Events;
+
+/// Mock REST API serving the generated collections.
+struct Api {
+    /// Collections of records, with their routes.
+    collections: Vec<Collection>,
+    /// Number of records of every collection.
+    count: u64,
+    /// Streams of events, with their routes.
+    events: Vec<Collection>,
+    /// Field set to the index of the records.
+    id_field: String,
+    /// Delay before every response.
+    latency: Option<Duration>,
+    /// Pace of the streams of events.
+    pace: Pace,
+}
+
+impl Api {
+    /// Creates, reads, updates or deletes a record of a stored collection.
+    fn crud(store: &Mutex<Store>, method: &Method, id: Option<u64>, body: &str) -> Res<Reply> {
+        let mut records = store.lock().map_err(|_err| eyre!("A request panicked"))?;
+        let parsed = || serde_json::from_str::<Value>(body).ok();
+        Ok(match (method, id, parsed()) {
+            (Method::Get, Some(index), _) => Reply::found(records.get(index).cloned()),
+            (Method::Post, None, Some(record)) =>
+                Reply { body: Some(records.create(record)), status: 201, total: None },
+            (Method::Put, Some(index), Some(record)) =>
+                Reply::found(records.replace(index, record)),
+            (Method::Patch, Some(index), Some(patch)) => Reply::found(records.patch(index, patch)),
+            (Method::Delete, Some(index), _) =>
+                if records.delete(index) {
+                    Reply { body: None, status: 204, total: None }
+                } else {
+                    Reply::found(None)
+                },
+            (Method::Post | Method::Put | Method::Patch, _, None) =>
+                Reply::error(400, "The body must be a JSON value"),
+            (_, _, _) => Reply::error(405, &format!("Method {method} is not allowed")),
+        })
+    }
+
+    /// Stream of events requested by `request`, if any.
+    fn events(&self, request: &Request) -> Option<&Collection> {
+        let path = request.url().split('?').next()?.trim_end_matches('/');
+        self.events
+            .iter()
+            .find(|events| *request.method() == Method::Get && events.path == path)
+    }
+
+    /// Answers a request, with the records generated with `data`.
+    fn handle(&self, mut data: Data, mut request: Request) {
+        if let Some(latency) = self.latency {
+            thread::sleep(latency);
+        }
+        if let Some(events) = self.events(&request) {
+            let start = start_index(&request);
+            let stream = Events::new(&events.pattern, data, start, self.pace);
+            let websocket = request.headers().iter().any(|header| {
+                header.field.equiv("Upgrade")
+                    && header.value.as_str().eq_ignore_ascii_case("websocket")
+            });
+            // The stream only ends when the client disconnects.
+            drop(if websocket {
+                stream.websocket(request)
+            } else {
+                stream.sse(request)
+            });
+            return;
+        }
+        let mut body = String::new();
+        let reply = request
+            .as_reader()
+            .read_to_string(&mut body)
+            .context("Failed to read the request")
+            .and_then(|_| self.reply(request.method(), request.url(), &body, &mut data))
+            .unwrap_or_else(|err| Reply::error(500, &format!("{err:#}")));
+        // The client may be gone, and there is no one else to tell.
+        drop(request.respond(reply.response()));
+    }
+
+    /// Page of the records of a collection, filtered and sorted with the
+    /// query parameters.
+    ///
+    /// Generated collections are only generated entirely if the records are
+    /// filtered or sorted.
+    fn list(&self, collection: &Collection, query: &str, data: &mut Data) -> Res<Reply> {
+        let Some(params) = Query::parse(query) else {
+            return Ok(Reply::error(400, "`page` and `per_page` must be positive integers"));
+        };
+        let (total, records) = match &collection.store {
+            Some(store) => params.select(
+                store
+                    .lock()
+                    .map_err(|_err| eyre!("A request panicked"))?
+                    .values()
+                    .cloned()
+                    .map(Ok),
+            )?,
+            None if params.is_plain() => (
+                self.count,
+                params
+                    .range(self.count)
+                    .map(|index| self.record(collection, index, data))
+                    .collect::<Res<_>>()?,
+            ),
+            None =>
+                params.select((0..self.count).map(|index| self.record(collection, index, data)))?,
+        };
+        Ok(Reply { body: Some(Value::Array(records)), status: 200, total: Some(total) })
+    }
+
+    /// Generates the record at `index` of a collection.
+    ///
+    /// If the record is an object, its index is written in the id field, so
+    /// that the record can be fetched by the id listed in the collection.
+    fn record(&self, collection: &Collection, index: u64, data: &mut Data) -> Res<Value> {
+        let mut record = generate_record(&collection.pattern, data, index)?;
+        set_id(&mut record, &self.id_field, index);
+        Ok(record)
+    }
+
+    /// Computes the response to a request on `url`.
+    fn reply(&self, method: &Method, url: &str, body: &str, data: &mut Data) -> Res<Reply> {
+        let (full_path, query) = url.split_once('?').unwrap_or((url, ""));
+        let path = full_path.trim_end_matches('/');
+        let Some((collection, id)) = self.route(path) else {
+            return Ok(Reply::error(404, &format!("No route for {path}")));
+        };
+        if *method == Method::Options {
+            return Ok(Reply { body: None, status: 204, total: None });
+        }
+        let index = match id.map(str::parse::<u64>) {
+            None => None,
+            Some(Ok(parsed)) => Some(parsed),
+            Some(Err(_)) => return Ok(Reply::found(None)),
+        };
+        match (&collection.store, method, index) {
+            (_, Method::Get, None) => self.list(collection, query, data),
+            (Some(store), _, _) => Self::crud(store, method, index, body),
+            (None, Method::Get, Some(found)) if found < self.count =>
+                Ok(Reply::found(Some(self.record(collection, found, data)?))),
+            (None, Method::Get, Some(_)) => Ok(Reply::found(None)),
+            (None, _, _) => Ok(Reply::error(405, &format!("Method {method} is not allowed"))),
+        }
+    }
+
+    /// Collection of a path, with the id of the record if the path is the
+    /// one of a record.
+    fn route<'path>(&self, path: &'path str) -> Option<(&Collection, Option<&'path str>)> {
+        self.collections.iter().find_map(|collection| {
+            if path == collection.path {
+                return Some((collection, None));
+            }
+            path.strip_prefix(&collection.path)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .filter(|id| !id.contains('/'))
+                .map(|id| (collection, Some(id)))
+        })
+    }
+}
+
+/// Collection of generated records, served on a route.
+struct Collection {
+    /// Route of the collection, e.g. `/users`.
+    path: String,
+    /// Pattern of the records.
+    pattern: Value,
+    /// Records of the collection with `--crud`. Otherwise, the records are
+    /// generated for every request.
+    store: Option<Mutex<Store>>,
+}
+
+/// Response to a request.
+struct Reply {
+    /// JSON body of the response, if any.
+    body: Option<Value>,
+    /// Status code of the response.
+    status: u16,
+    /// Total number of records of the collection, for the pages of records.
+    total: Option<u64>,
+}
+
+impl Reply {
+    /// Response with an error message.
+    fn error(status: u16, message: &str) -> Self {
+        Self { body: Some(json!({ "error": message })), status, total: None }
+    }
+
+    /// Response with a record, or an error if it wasn't found.
+    fn found(record: Option<Value>) -> Self {
+        record.map_or_else(
+            || Self::error(404, "No record with this id"),
+            |value| Self { body: Some(value), status: 200, total: None },
+        )
+    }
+
+    /// HTTP response, with the headers of a JSON API.
+    fn response(self) -> Response<Cursor<Vec<u8>>> {
+        let content = self.body.map(|body| body.to_string()).unwrap_or_default();
+        let mut response = Response::from_data(content.into_bytes()).with_status_code(self.status);
+        let total = self.total.map(|total| total.to_string());
+        for (name, value) in [
+            ("Content-Type", Some("application/json")),
+            // The API is used from the dev servers of frontends.
+            ("Access-Control-Allow-Origin", Some("*")),
+            ("Access-Control-Allow-Methods", Some("GET, POST, PUT, PATCH, DELETE, OPTIONS")),
+            ("Access-Control-Allow-Headers", Some("Content-Type")),
+            ("Access-Control-Expose-Headers", total.as_ref().map(|_| "X-Total-Count")),
+            ("X-Total-Count", total.as_deref()),
+        ] {
+            if let Some(header) = value.and_then(|text| Header::from_bytes(name, text).ok()) {
+                response.add_header(header);
+            }
+        }
+        response
+    }
+}
+
+/// Route of a collection, and the file of the pattern of its records.
+#[derive(Clone, Debug)]
+pub struct Route {
+    /// Path to the file containing the pattern of the records.
+    file: String,
+    /// Route of the collection, e.g. `/users`.
+    path: String,
+}
+
+impl Route {
+    /// Reads the pattern of the collection.
+    fn load(&self) -> Res<Collection> {
+        let content = fs::read_to_string(&self.file)
+            .with_context(|| format!("Failed to read {}", self.file))?;
+        let (pattern, _) = PatternFormat::from_path(&self.file)
+            .parse(&content)
+            .with_context(|| format!("Invalid pattern in {}", self.file))?;
+        Ok(Collection { path: self.path.clone(), pattern, store: None })
+    }
+}
+
+impl FromStr for Route {
+    type Err = color_eyre::Report;
+
+    fn from_str(value: &str) -> Res<Self> {
+        let Some((path, file)) = value.split_once('=') else {
+            bail!("Missing `=` in route. Expected: path=file, e.g. /users=user.json")
+        };
+        if !path.starts_with('/') {
+            bail!("Routes must start with `/`, but got `{path}`")
+        }
+        Ok(Self { file: file.to_owned(), path: path.trim_end_matches('/').to_owned() })
+    }
+}
+
+/// Options of the mock REST API.
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Number of records of every collection.
+    #[arg(short, long, default_value_t = 100)]
+    count: u64,
+    /// Generate the collections when the server starts, and keep them in
+    /// memory, to create, update and delete records with `POST /users`,
+    /// `PUT /users/:id`, `PATCH /users/:id` and `DELETE /users/:id`.
+    #[arg(long, default_value_t = false)]
+    crud: bool,
+    /// Route of a stream of events and the file of the pattern of its
+    /// records, e.g. '/ticks=tick.json'. The records are pushed at `--rate`
+    /// with Server-Sent Events, or with a WebSocket if the client asks for
+    /// an upgrade.
+    #[arg(short, long = "events")]
+    events: Vec<Route>,
+    /// Field of the records set to their index, so that the records listed
+    /// in a collection can be fetched by id. Empty to keep the generated
+    /// fields.
+    #[arg(long, default_value = "id")]
+    id_field: String,
+    /// Delay before every response, e.g. '200ms'.
+    #[arg(long)]
+    latency: Option<humantime::Duration>,
+    /// Port to listen on, on localhost.
+    #[arg(long, default_value_t = 3000)]
+    port: u16,
+    /// Number of events pushed per period of time in the streams of events,
+    /// e.g. '10/s' or '30/min'.
+    #[arg(long, default_value = "1/s")]
+    rate: Rate,
+    /// Route of a collection and the file of the pattern of its records,
+    /// e.g. '/users=user.json'. `GET /users` lists the records by pages, and
+    /// `GET /users/:id` returns the record of the given id.
+    #[arg(short, long = "route", required_unless_present = "events")]
+    routes: Vec<Route>,
+    /// Generate with a given random seed. Otherwise, a seed is chosen at
+    /// random, so the records are the same while the server runs.
+    #[arg(short, long)]
+    seed: Option<u64>,
+    /// Add custom data types, with the format 'Type:Value1|Value2'
+    #[arg(short, long = "user")]
+    user_defined: Vec<String>,
+}
+
+impl ServeArgs {
+    /// Serves the collections until interrupted, and writes the address of
+    /// the server to `writer`.
+    pub fn run<W: Write>(self, writer: &mut W) -> Res {
+        let mut collections = self
+            .routes
+            .iter()
+            .map(Route::load)
+            .collect::<Res<Vec<_>>>()?;
+        let events = self
+            .events
+            .iter()
+            .map(Route::load)
+            .collect::<Res<Vec<_>>>()?;
+        let data = Data::new(self.user_defined, Some(self.seed.unwrap_or_else(rand::random)))?;
+        if self.crud {
+            let mut initial_data = data.fork();
+            for collection in &mut collections {
+                let records = (0..self.count)
+                    .map(|index| generate_record(&collection.pattern, &mut initial_data, index))
+                    .collect::<Res<_>>()?;
+                collection.store = Some(Mutex::new(Store::new(self.id_field.clone(), records)));
+            }
+        }
+        let server = Server::http(("127.0.0.1", self.port))
+            .map_err(|err| eyre!("Failed to listen on port {}: {err}", self.port))?;
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map_or(self.port, |addr| addr.port());
+        for collection in &collections {
+            writeln!(writer, "Serving http://127.0.0.1:{port}{}", collection.path)
+                .context("Failed to write output")?;
+        }
+        for stream in &events {
+            writeln!(writer, "Streaming http://127.0.0.1:{port}{}", stream.path)
+                .context("Failed to write output")?;
+        }
+        writer.flush().context("Failed to write output")?;
+
+        let api = Api {
+            collections,
+            count: self.count,
+            events,
+            id_field: self.id_field,
+            latency: self.latency.map(Into::into),
+            pace: Pace {
+                burst: NonZeroU32::MIN,
+                interval: self.rate.interval(NonZeroU32::MIN),
+                jitter: 0,
+            },
+        };
+        thread::scope(|scope| {
+            for request in server.incoming_requests() {
+                let request_data = data.fork();
+                let request_api = &api;
+                scope.spawn(move || request_api.handle(request_data, request));
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Index of the first event of a stream: after the `Last-Event-ID` of a
+/// reconnecting client, at the `offset` query parameter, or 0.
+fn start_index(request: &Request) -> u64 {
+    let last_event = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Last-Event-ID"))
+        .and_then(|header| header.value.as_str().parse::<u64>().ok())
+        .map(|last| last.saturating_add(1));
+    let offset = || {
+        request
+            .url()
+            .split_once('?')?
+            .1
+            .split('&')
+            .find_map(|param| param.strip_prefix("offset="))?
+            .parse()
+            .ok()
+    };
+    last_event.or_else(offset).unwrap_or_default()
+}
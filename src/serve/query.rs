@@ -0,0 +1,149 @@
+//! Parse the query parameters of the requests that list records, to filter,
+//! sort and paginate them.
+
+use core::cmp::Ordering;
+use core::ops::Range;
+
+use serde_json::Value;
+
+use crate::Res;
+
+/// Number of records of a page, if not given with `per_page`.
+const PER_PAGE: u64 = 10;
+
+/// Parameters of a request listing records.
+pub struct Query {
+    /// Fields that the records must have, with their values.
+    filters: Vec<(String, String)>,
+    /// Number of the page, from 1.
+    page: u64,
+    /// Number of records of a page.
+    per_page: u64,
+    /// Field to sort the records by, and whether the order is descending.
+    sort: Option<(String, bool)>,
+}
+
+impl Query {
+    /// Whether the records are neither filtered nor sorted, so a page only
+    /// depends on the number of records.
+    pub const fn is_plain(&self) -> bool {
+        self.filters.is_empty() && self.sort.is_none()
+    }
+
+    /// Parses a query string, e.g. `page=2&sort=-age&name=Ada`.
+    ///
+    /// Returns [`None`] if `page` or `per_page` isn't a positive integer.
+    pub fn parse(query: &str) -> Option<Self> {
+        let mut parsed = Self { filters: vec![], page: 1, per_page: PER_PAGE, sort: None };
+        for param in query.split('&').filter(|param| !param.is_empty()) {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            let decoded = decode(value);
+            match key {
+                "page" => parsed.page = decoded.parse().ok().filter(|&page| page > 0)?,
+                "per_page" => parsed.per_page = decoded.parse().ok().filter(|&size| size > 0)?,
+                "sort" => {
+                    let (name, descending) = decoded
+                        .strip_prefix('-')
+                        .map_or((decoded.as_str(), false), |field| (field, true));
+                    parsed.sort = Some((name.to_owned(), descending));
+                }
+                _ => parsed.filters.push((decode(key), decoded)),
+            }
+        }
+        Some(parsed)
+    }
+
+    /// Indices of the records of the page, among `total` records.
+    pub fn range(&self, total: u64) -> Range<u64> {
+        let start = self
+            .page
+            .saturating_sub(1)
+            .saturating_mul(self.per_page)
+            .min(total);
+        start..start.saturating_add(self.per_page).min(total)
+    }
+
+    /// Filters and sorts the records, and returns the number of matching
+    /// records with the records of the page.
+    pub fn select<I: Iterator<Item = Res<Value>>>(&self, records: I) -> Res<(u64, Vec<Value>)> {
+        let mut matching = vec![];
+        for record in records {
+            let value = record?;
+            if self.filters.iter().all(|(name, expected)| {
+                field(&value, name).is_some_and(|found| text(found) == *expected)
+            }) {
+                matching.push(value);
+            }
+        }
+        if let Some((name, descending)) = &self.sort {
+            matching.sort_by(|first, second| {
+                let order = compare(field(first, name), field(second, name));
+                if *descending { order.reverse() } else { order }
+            });
+        }
+        let total = u64::try_from(matching.len()).unwrap_or(u64::MAX);
+        let range = self.range(total);
+        let page = matching
+            .into_iter()
+            .skip(usize::try_from(range.start).unwrap_or(usize::MAX))
+            .take(usize::try_from(range.end.saturating_sub(range.start)).unwrap_or(usize::MAX))
+            .collect();
+        Ok((total, page))
+    }
+}
+
+/// Orders the values of a field: numbers by value, other values by their
+/// text, and missing fields last.
+fn compare(first: Option<&Value>, second: Option<&Value>) -> Ordering {
+    match (first, second) {
+        (Some(Value::Number(left)), Some(Value::Number(right))) => left
+            .as_f64()
+            .partial_cmp(&right.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(left), Some(right)) => text(left).cmp(&text(right)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Decodes a percent-encoded component of a query string.
+fn decode(component: &str) -> String {
+    let mut bytes = vec![];
+    let mut rest = component.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        let hex = after
+            .get(..2)
+            .and_then(|digits| str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        match (byte, hex) {
+            (b'%', Some(decoded)) => {
+                bytes.push(decoded);
+                rest = after.get(2..).unwrap_or_default();
+            }
+            (b'+', _) => {
+                bytes.push(b' ');
+                rest = after;
+            }
+            (_, _) => {
+                bytes.push(byte);
+                rest = after;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Value of a field of a record, with `.` to access nested fields.
+fn field<'record>(record: &'record Value, name: &str) -> Option<&'record Value> {
+    record.pointer(&format!("/{}", name.replace('.', "/")))
+}
+
+/// Text of a value, to compare it with the query parameters.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.to_owned(),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::Array(_) | Value::Object(_) =>
+            value.to_string(),
+    }
+}
@@ -0,0 +1,103 @@
+//! Keep the records of a collection in memory, to create, update and delete
+//! them.
+
+use alloc::collections::BTreeMap;
+
+use serde_json::{Map, Value};
+
+/// Records of a collection, by id.
+pub struct Store {
+    /// Field of the records set to their id, if not empty.
+    id_field: String,
+    /// Id of the next created record.
+    next_id: u64,
+    /// Records, by id.
+    records: BTreeMap<u64, Value>,
+}
+
+impl Store {
+    /// Adds a new record, and returns it with its id.
+    pub fn create(&mut self, record: Value) -> Value {
+        let id = self.next_id;
+        self.next_id = self.next_id.saturating_add(1);
+        self.insert(id, record)
+    }
+
+    /// Removes a record, and returns whether it existed.
+    pub fn delete(&mut self, id: u64) -> bool {
+        self.records.remove(&id).is_some()
+    }
+
+    /// Record of the given id, if any.
+    pub fn get(&self, id: u64) -> Option<&Value> {
+        self.records.get(&id)
+    }
+
+    /// Sets the id field of the record, stores it, and returns it.
+    fn insert(&mut self, id: u64, mut record: Value) -> Value {
+        set_id(&mut record, &self.id_field, id);
+        self.records.insert(id, record.clone());
+        record
+    }
+
+    /// Stores the initial records, with ids from 0.
+    pub fn new(id_field: String, records: Vec<Value>) -> Self {
+        let mut store = Self { id_field, next_id: 0, records: BTreeMap::new() };
+        for record in records {
+            store.create(record);
+        }
+        store
+    }
+
+    /// Applies a JSON merge patch to a record, and returns it, if it exists.
+    ///
+    /// The fields of the patch replace the ones of the record, and the fields
+    /// set to `null` are removed.
+    pub fn patch(&mut self, id: u64, patch: Value) -> Option<Value> {
+        let mut record = self.records.get(&id)?.clone();
+        merge(&mut record, patch);
+        Some(self.insert(id, record))
+    }
+
+    /// Replaces a record, and returns it, if it exists.
+    pub fn replace(&mut self, id: u64, record: Value) -> Option<Value> {
+        self.records
+            .contains_key(&id)
+            .then(|| self.insert(id, record))
+    }
+
+    /// Records, by increasing id.
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.records.values()
+    }
+}
+
+/// Merges a JSON merge patch into a value, as defined by RFC 7396.
+fn merge(value: &mut Value, patch: Value) {
+    let Value::Object(fields) = patch else {
+        *value = patch;
+        return;
+    };
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    if let Value::Object(object) = value {
+        for (key, field) in fields {
+            if field.is_null() {
+                object.remove(&key);
+            } else {
+                merge(object.entry(key).or_insert(Value::Null), field);
+            }
+        }
+    }
+}
+
+/// Sets the field `id_field` of a record to its id, if the record is an
+/// object and `id_field` isn't empty.
+pub fn set_id(record: &mut Value, id_field: &str, id: u64) {
+    if let Value::Object(object) = record
+        && !id_field.is_empty()
+    {
+        object.insert(id_field.to_owned(), Value::from(id));
+    }
+}
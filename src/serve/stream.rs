@@ -0,0 +1,92 @@
+//! Push generated records as events, over Server-Sent Events or WebSocket.
+
+use std::io::Write as _;
+
+use color_eyre::eyre::{Context as _, ContextCompat as _};
+use serde_json::Value;
+use tiny_http::{Header, Request, Response};
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+use crate::Res;
+use crate::data::Data;
+use crate::json::{Pace, Pacer, generate_record};
+
+/// Stream of the records of a pattern, generated at a given pace.
+///
+/// Every connection has its own stream, starting at the record `index`, so
+/// the streams are the same for every connection with a seed.
+pub struct Events<'pattern> {
+    /// Generator of the records of this connection.
+    data: Data,
+    /// Index of the next record.
+    index: u64,
+    /// Pacer of the records.
+    pacer: Pacer,
+    /// Pattern of the records.
+    pattern: &'pattern Value,
+}
+
+impl<'pattern> Events<'pattern> {
+    /// Starts a stream of records at `index`.
+    pub fn new(pattern: &'pattern Value, data: Data, index: u64, pace: Pace) -> Self {
+        Self { data, index, pacer: Pacer::new(pace), pattern }
+    }
+
+    /// Waits until the next record is due, and returns it with its index.
+    fn next_record(&mut self) -> Res<(u64, Value)> {
+        self.pacer.wait();
+        let index = self.index;
+        self.index = self.index.saturating_add(1);
+        Ok((index, generate_record(self.pattern, &mut self.data, index)?))
+    }
+
+    /// Sends the records as Server-Sent Events, with their index as event
+    /// id, until the client disconnects.
+    pub fn sse(mut self, request: Request) -> Res {
+        let mut writer = request.into_writer();
+        writer
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+                  Access-Control-Allow-Origin: *\r\n\r\n",
+            )
+            .context("Failed to start the event stream")?;
+        loop {
+            let (index, record) = self.next_record()?;
+            writer
+                .write_all(format!("id: {index}\ndata: {record}\n\n").as_bytes())
+                .and_then(|()| writer.flush())
+                .context("The client disconnected")?;
+        }
+    }
+
+    /// Upgrades the connection to a WebSocket, and sends the records as text
+    /// messages until the client disconnects.
+    pub fn websocket(mut self, request: Request) -> Res {
+        let key = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+            .context("Missing Sec-WebSocket-Key header")?
+            .value
+            .to_string();
+        let accept = Header::from_bytes("Sec-WebSocket-Accept", derive_accept_key(key.as_bytes()))
+            .ok()
+            .context("Invalid Sec-WebSocket-Key header")?;
+        let upgrade = Header::from_bytes("Upgrade", "websocket")
+            .ok()
+            .context("Invalid Upgrade header")?;
+        let response = Response::empty(101u16)
+            .with_header(upgrade)
+            .with_header(accept);
+        let mut socket =
+            WebSocket::from_raw_socket(request.upgrade("websocket", response), Role::Server, None);
+        loop {
+            let (_, record) = self.next_record()?;
+            socket
+                .send(Message::text(record.to_string()))
+                .context("The client disconnected")?;
+        }
+    }
+}
@@ -1,12 +1,83 @@
 #![expect(clippy::panic, clippy::unwrap_used, reason = "test")]
 
-use core::iter::once;
+use core::iter::{once, repeat_with};
+use core::ops::Deref;
+use core::time::Duration;
 use std::collections::HashSet;
+use std::ffi::OsString;
+use std::io::{BufRead as _, BufReader, Cursor, Read as _, Write as _};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use std::{env, fs, process, thread};
 
+use arrow_ipc::reader::FileReader;
 use clap::Parser as _;
+use flate2::read::GzDecoder;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
 use serde_json::Value;
 
-use crate::clap::{Action, CliArgs};
+use crate::clap::CliArgs;
+
+/// Path in the temporary directory, removed with its content when dropped,
+/// so that it is cleaned up even if the test fails.
+struct TempPath(PathBuf);
+
+impl TempPath {
+    /// Path named after `name`, unique to this run of the tests.
+    fn new(name: &str) -> Self {
+        Self(env::temp_dir().join(format!("random-json-{}-{name}", process::id())))
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            drop(fs::remove_dir_all(&self.0));
+        } else {
+            drop(fs::remove_file(&self.0));
+        }
+    }
+}
+
+#[test]
+fn placeholders() {
+    let out = run([
+        "",
+        "-p",
+        r#""Word""#,
+        "-c",
+        "3",
+        "--offset",
+        "2",
+        "-b",
+        "{i}/{n}=",
+        "-a",
+        ",",
+        "--after-last",
+        ";",
+    ]);
+    let prefixes = out
+        .split([',', ';'])
+        .map(|record| record.split_once('=').map(|(prefix, _)| prefix))
+        .collect::<Vec<_>>();
+    assert_eq!(prefixes, [Some("2/3"), Some("3/3"), Some("4/3"), None]);
+    assert!(out.ends_with(';'));
+}
 
 #[test]
 fn repeat() {
@@ -32,8 +103,21 @@ fn repeat() {
 }
 
 fn run<const N: usize>(args: [&str; N]) -> String {
-    match CliArgs::parse_from(args).dispatch().1.and_then(Action::run) {
-        Ok(out) => out,
+    String::from_utf8(run_bytes(args)).unwrap()
+}
+
+fn run_bytes<I, T>(args: I) -> Vec<u8>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let mut out = vec![];
+    match CliArgs::parse_from(args)
+        .dispatch()
+        .1
+        .and_then(|act| act.run(&mut out))
+    {
+        Ok(()) => out,
         Err(err) => panic!("{err:?}"),
     }
 }
@@ -57,6 +141,45 @@ fn schema() {
     );
 }
 
+#[test]
+#[expect(clippy::big_endian_bytes, reason = "lengths are big endian")]
+fn binary() {
+    let schema = r#"{"int": "0..5", "float": "1.0..2.0", "name": "FirstName"}"#;
+    let check = |record: &Value| {
+        assert!(record.get("int").unwrap().is_u64());
+        assert!(record.get("float").unwrap().is_f64());
+        assert!(record.get("name").unwrap().is_string());
+    };
+
+    let msgpack = run_bytes([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "--format",
+        "msgpack",
+        "--framing",
+        "length-delimited",
+    ]);
+    let mut rest = msgpack.as_slice();
+    for _ in 0u8..3 {
+        let (length, after_length) = rest.split_first_chunk::<4>().unwrap();
+        let (encoded, after_record) =
+            after_length.split_at(u32::from_be_bytes(*length).try_into().unwrap());
+        check(&rmp_serde::from_slice::<Value>(encoded).unwrap());
+        rest = after_record;
+    }
+    assert!(rest.is_empty());
+
+    let cbor = run_bytes(["", "-p", schema, "-c", "3", "--format", "cbor"]);
+    let mut reader = Cursor::new(cbor);
+    for _ in 0u8..3 {
+        check(&ciborium::from_reader::<Value, _>(&mut reader).unwrap());
+    }
+    assert_eq!(reader.position(), u64::try_from(reader.get_ref().len()).unwrap());
+}
+
 #[test]
 fn conflict() {
     for (first, second) in [("-p", "-f"), ("-s", "-l"), ("-p", "-i")] {
@@ -70,3 +193,909 @@ fn list() {
     assert!(out.contains("Country"));
     assert!(out.contains("FirstName"));
 }
+
+#[test]
+fn template() {
+    let path = TempPath::new("template.jinja");
+    fs::write(
+        &path,
+        "{% block header %}[{% endblock %}\
+         {% block record %}{{ record_index }}:{{ name }}{% if not record_last %},{% endif %}{% endblock %}\
+         {% block footer %}]{% endblock %}",
+    )
+    .unwrap();
+    let schema = r#"{"name": "Word"}"#;
+    let out = run([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "--offset",
+        "4",
+        "--template",
+        path.to_str().unwrap(),
+    ]);
+    let records = out
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap()
+        .split(',')
+        .map(|record| record.split_once(':').unwrap().0)
+        .collect::<Vec<_>>();
+    assert_eq!(records, ["4", "5", "6"]);
+}
+
+#[test]
+fn threads() {
+    let schema = r#"{"name": "FirstName", "family": "LastName[1]", "age?": "0..100"}"#;
+    let single = run(["", "-p", schema, "-c", "3000", "-s", "7"]);
+    let multi = run(["", "-p", schema, "-c", "3000", "-s", "7", "--threads", "4"]);
+    assert_eq!(single, multi);
+}
+
+#[test]
+fn shards() {
+    let schema = r#"{"name": "FirstName", "family": "LastName[1]", "age?": "0..100"}"#;
+    let single = run(["", "-p", schema, "-c", "10", "-s", "3"]);
+    let sharded = ["1/3", "2/3", "3/3"]
+        .map(|shard| run(["", "-p", schema, "-c", "10", "-s", "3", "--shard", shard]))
+        .concat();
+    assert_eq!(single, sharded);
+    let last = run(["", "-p", schema, "-c", "1", "-s", "3", "--offset", "9"]);
+    assert!(single.ends_with(&last));
+
+    let numbered = ["1/2", "2/2"]
+        .map(|shard| {
+            run([
+                "",
+                "-p",
+                r#""Word""#,
+                "-c",
+                "4",
+                "-b",
+                "{i}/{n} ",
+                "--shard",
+                shard,
+            ])
+        })
+        .concat();
+    let prefixes = numbered
+        .lines()
+        .map(|line| line.split(' ').next().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(prefixes, ["0/4", "1/4", "2/4", "3/4"]);
+    let unique = CliArgs::parse_from(["", "-p", r#""0..9*""#, "-c", "4", "--shard", "1/2"]);
+    let mut out = vec![];
+    let err = unique
+        .dispatch()
+        .1
+        .and_then(|act| act.run(&mut out))
+        .unwrap_err();
+    assert!(err.to_string().contains("Unique"));
+}
+
+#[test]
+fn stable_fields() {
+    let before = run([
+        "",
+        "-p",
+        r#"{"name": "FirstName", "age": "0..100"}"#,
+        "-c",
+        "5",
+        "-s",
+        "1",
+    ]);
+    let after = run([
+        "",
+        "-p",
+        r#"{"email": "Email", "name": "FirstName", "job?": "Job", "age": "0..100"}"#,
+        "-c",
+        "5",
+        "-s",
+        "1",
+    ]);
+    let fields = |out: &str| {
+        serde_json::Deserializer::from_str(out)
+            .into_iter::<Value>()
+            .map(|record| {
+                let object = record.unwrap();
+                (object.get("name").cloned(), object.get("age").cloned())
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(fields(&before), fields(&after));
+}
+
+#[test]
+fn stable_array_items() {
+    let out = run([
+        "",
+        "-p",
+        r#"{"people": [{"name": "FirstName", "id": "0..1000000"}, 5]}"#,
+        "-s",
+        "1",
+        "--format",
+        "ndjson",
+    ]);
+    let record = serde_json::from_str::<Value>(&out).unwrap();
+    let ids = record
+        .get("people")
+        .and_then(Value::as_array)
+        .unwrap()
+        .iter()
+        .map(|person| person.get("id").and_then(Value::as_u64).unwrap())
+        .collect::<HashSet<_>>();
+    assert_eq!(ids.len(), 5);
+}
+
+#[test]
+fn formats() {
+    let schema = r#"{"name": "FirstName", "age?": "0..100"}"#;
+    for indent in ["0", "4"] {
+        let out = run([
+            "",
+            "-p",
+            schema,
+            "-c",
+            "3",
+            "--format",
+            "json-array",
+            "--indent",
+            indent,
+        ]);
+        let Ok(Value::Array(data)) = serde_json::from_str::<Value>(&out) else {
+            panic!("{out} is not an array")
+        };
+        assert_eq!(data.len(), 3);
+    }
+    let out = run(["", "-p", schema, "-c", "3", "--format", "ndjson"]);
+    assert_eq!(out.lines().count(), 3);
+    for line in out.lines() {
+        serde_json::from_str::<Value>(line).unwrap();
+    }
+}
+
+#[test]
+fn documents() {
+    let schema = r#"{"name": "FirstName", "n": "Int", "note!": "a<b", "tags": ["Word", 2]}"#;
+    let yaml = run(["", "-p", schema, "-c", "3", "--format", "yaml"]);
+    let records = serde_norway::from_str::<Vec<Value>>(&yaml).unwrap();
+    assert_eq!(records.len(), 3);
+    let toml = run([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "--format",
+        "toml",
+        "--record-name",
+        "user",
+    ]);
+    let table = toml::from_str::<toml::Table>(&toml).unwrap();
+    assert_eq!(table.get("user").unwrap().as_array().unwrap().len(), 3);
+    let xml = run([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "--format",
+        "xml",
+        "--xml-fields",
+        "attributes",
+        "--indent",
+        "0",
+    ]);
+    assert!(
+        xml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<records><record name=\"")
+    );
+    assert_eq!(xml.matches("note=\"a&lt;b\"").count(), 3);
+    assert_eq!(xml.matches("<tags>").count(), 6);
+    assert!(xml.ends_with("</record></records>\n"));
+
+    let names = run([
+        "",
+        "-p",
+        r#"{"first name": "FirstName", "1x": "0..9"}"#,
+        "--format",
+        "xml",
+        "--indent",
+        "0",
+    ]);
+    assert!(names.contains("<first_name>"));
+    assert!(names.contains("<_1x>"));
+    let nulls = run(["", "-p", r#"{"a!": null, "b": "Word"}"#, "--format", "toml"]);
+    let null_table = toml::from_str::<toml::Table>(&nulls).unwrap();
+    let record = null_table
+        .get("record")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .first()
+        .unwrap();
+    assert!(record.get("a").is_none());
+    assert!(record.get("b").is_some());
+}
+
+#[test]
+fn csv() {
+    let schema = r#"{"name": "FirstName", "email?": "Email", "address": {"city": "City"}}"#;
+    let out = run([
+        "", "-p", schema, "-c", "20", "--format", "csv", "--null", "NULL",
+    ]);
+    let mut lines = out.lines();
+    assert_eq!(lines.next(), Some("name,email,address.city"));
+    assert_eq!(lines.count(), 20);
+    let exploded = run([
+        "",
+        "-p",
+        r#"{"id!": 1, "tags": ["Word", 2]}"#,
+        "--format",
+        "tsv",
+        "--arrays",
+        "explode",
+    ]);
+    assert_eq!(
+        exploded
+            .lines()
+            .filter(|line| line.starts_with("1\t"))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn tabular_pattern() {
+    let pattern = "id,name,age?,jobs\n0..10,FirstName,0..100,\"[\"\"Job\"\", 2]\"";
+    let csv = run(["", "-p", pattern, "-c", "5", "--null", "NULL"]);
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("id,name,age,jobs"));
+    assert_eq!(lines.count(), 5);
+    let out = run([
+        "",
+        "-p",
+        "name\tage\nFirstName\t0..100",
+        "--format",
+        "ndjson",
+    ]);
+    let Ok(Value::Object(data)) = serde_json::from_str::<Value>(&out) else {
+        panic!("{out} is not an object")
+    };
+    assert_eq!(data.keys().map(String::as_str).collect::<Vec<_>>(), ["name", "age"]);
+}
+
+#[test]
+fn sql() {
+    let schema = r#"{"name": "FirstName", "quote!": "it's", "email?": "Email"}"#;
+    let out = run([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "5",
+        "--format",
+        "sql",
+        "--table",
+        "people",
+        "--batch-size",
+        "2",
+    ]);
+    assert_eq!(
+        out.matches("INSERT INTO \"people\" (\"name\", \"quote\", \"email\") VALUES")
+            .count(),
+        3
+    );
+    assert_eq!(out.matches("'it''s'").count(), 5);
+    assert_eq!(out.matches(';').count(), 3);
+}
+
+#[test]
+fn es_bulk() {
+    let schema = r#"{"_id": "0..1000", "name": "FirstName"}"#;
+    let out = run([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "--format",
+        "es-bulk",
+        "--index",
+        "people",
+        "--id-field",
+        "_id",
+    ]);
+    let lines = out.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 6);
+    for pair in lines.chunks(2) {
+        let [action, document] = pair else {
+            panic!("{pair:?}")
+        };
+        let action_json = serde_json::from_str::<Value>(action).unwrap();
+        assert_eq!(action_json.pointer("/index/_index"), Some(&Value::from("people")));
+        assert!(action_json.pointer("/index/_id").unwrap().is_string());
+        assert!(!document.contains("_id"));
+    }
+    let renamed = run([
+        "",
+        "-p",
+        r#"{"_id": "0..10", "key": "10..20"}"#,
+        "--format",
+        "es-bulk",
+        "--index",
+        "people",
+        "--id-field",
+        "key",
+    ]);
+    let (action, document) = renamed.trim_end().split_once('\n').unwrap();
+    let document_json = serde_json::from_str::<Value>(document).unwrap();
+    assert_eq!(document_json.get("_id"), None);
+    assert_eq!(
+        serde_json::from_str::<Value>(action)
+            .unwrap()
+            .pointer("/index/_id"),
+        Some(&Value::from(document_json.get("key").unwrap().to_string()))
+    );
+}
+
+#[test]
+fn mongo() {
+    let schema = r#"{"_id": "ObjectId", "created": "Date", "count": "0..10", "ids": ["ObjectId", 2], "raw!": {"count": 1}}"#;
+    let out = run(["", "-p", schema, "-c", "5", "--format", "mongo"]);
+    assert_eq!(out.lines().count(), 5);
+    for line in out.lines() {
+        let document = serde_json::from_str::<Value>(line).unwrap();
+        let field = |pointer: &str| document.pointer(pointer).unwrap().as_str().unwrap();
+        assert_eq!(field("/_id/$oid").len(), 24);
+        assert!(field("/created/$date").ends_with('Z'));
+        assert!(field("/count/$numberLong").parse::<u8>().unwrap() < 10);
+        assert_eq!(field("/ids/1/$oid").len(), 24);
+        assert_eq!(document.pointer("/raw/count"), Some(&Value::from(1u8)));
+    }
+}
+
+#[test]
+fn pg_copy() {
+    let schema =
+        r#"{"name": "FirstName", "tab!": "a\tb", "age?": "0..100", "tags": ["Word", 2, 3]}"#;
+    let text = run([
+        "", "-p", schema, "-c", "20", "-s", "1", "--format", "pg-copy", "--table", "people",
+    ]);
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines.first(),
+        Some(&"COPY \"people\" (\"name\", \"tab\", \"age\", \"tags\") FROM STDIN;")
+    );
+    assert_eq!(lines.last(), Some(&"\\."));
+    assert_eq!(lines.len(), 22);
+    assert!(text.contains("\ta\\tb\t"));
+    assert!(text.contains("\t\\N\t"));
+    let binary = run_bytes([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "20",
+        "-s",
+        "1",
+        "--format",
+        "pg-copy-binary",
+    ]);
+    assert!(binary.starts_with(b"PGCOPY\n\xff\r\n\0"));
+    assert!(binary.ends_with(&[0xff, 0xff]));
+    let negative = run_bytes(["", "-p", r#"{"a!": -5}"#, "--format", "pg-copy-binary"]);
+    // -5 as a big endian bigint, then the trailer.
+    assert!(negative.ends_with(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfb, 0xff, 0xff]));
+    let mut out = vec![];
+    let err = CliArgs::parse_from([
+        "",
+        "-p",
+        r#"{"n": "Int"}"#,
+        "-s",
+        "1",
+        "--format",
+        "pg-copy-binary",
+    ])
+    .dispatch()
+    .1
+    .and_then(|act| act.run(&mut out))
+    .unwrap_err();
+    assert!(err.to_string().contains("bigint"));
+    assert!(out.is_empty());
+}
+
+#[test]
+fn columnar() {
+    let schema = r#"{"id": "0..100", "name": "FirstName", "address?": {"city": "City"}, "tags": ["Word", 0, 3]}"#;
+    let arrow = run_bytes([
+        "", "-p", schema, "-c", "100", "-s", "1", "--format", "arrow",
+    ]);
+    let arrow_reader = FileReader::try_new(Cursor::new(arrow), None).unwrap();
+    let names = arrow_reader
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| (field.name().to_owned(), field.is_nullable()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [
+            ("id", false),
+            ("name", false),
+            ("address", true),
+            ("tags", false)
+        ]
+        .map(|(name, nullable)| (name.to_owned(), nullable))
+    );
+    let rows = arrow_reader
+        .map(|batch| batch.unwrap().num_rows())
+        .sum::<usize>();
+    assert_eq!(rows, 100);
+
+    let path = TempPath::new("columnar.parquet");
+    let parquet = run_bytes([
+        "", "-p", schema, "-c", "100", "-s", "1", "--format", "parquet",
+    ]);
+    fs::write(&path, parquet).unwrap();
+    let parquet_reader =
+        ParquetRecordBatchReaderBuilder::try_new(fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(parquet_reader.metadata().file_metadata().num_rows(), 100);
+}
+
+#[test]
+fn compress() {
+    let schema = r#"{"name": "FirstName"}"#;
+    let plain = run(["", "-p", schema, "-c", "3", "-s", "1", "--format", "ndjson"]);
+    let gzip = run_bytes([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "-s",
+        "1",
+        "--format",
+        "ndjson",
+        "--compress",
+        "gzip",
+    ]);
+    let mut decoded = String::new();
+    GzDecoder::new(gzip.as_slice())
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, plain);
+
+    let dir = TempPath::new("zst");
+    let out = dir.join("out.ndjson.zst");
+    run([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "-s",
+        "1",
+        "--format",
+        "ndjson",
+        "-o",
+        out.to_str().unwrap(),
+        "--split-every",
+        "2",
+    ]);
+    let chunks = ["out-00000.ndjson.zst", "out-00001.ndjson.zst"]
+        .map(|name| zstd::decode_all(fs::File::open(dir.join(name)).unwrap()).unwrap())
+        .concat();
+    assert_eq!(String::from_utf8(chunks).unwrap(), plain);
+}
+
+#[test]
+fn limits() {
+    let schema = r#"{"name": "FirstName"}"#;
+    let sized = run(["", "-p", schema, "--format", "ndjson", "--size", "1KB"]);
+    let last = sized.trim_end().rsplit('\n').next().unwrap();
+    assert!(sized.len() >= 1000);
+    assert!(sized.len() < 1000 + last.len() + 1);
+
+    let timed = run(["", "-p", schema, "--format", "ndjson", "--duration", "50ms"]);
+    assert!(timed.lines().count() > 0);
+    CliArgs::try_parse_from(["", "-p", schema, "-c", "2", "--size", "1KB"]).unwrap_err();
+
+    let statements = run([
+        "",
+        "-p",
+        schema,
+        "--format",
+        "sql",
+        "--table",
+        "t",
+        "--batch-size",
+        "100",
+        "--size",
+        "200B",
+    ]);
+    assert!(statements.ends_with(");\n"));
+    CliArgs::try_parse_from(["", "-p", schema, "--after-last", "", "--forever"]).unwrap_err();
+    let placeholder = CliArgs::parse_from(["", "-p", schema, "-b", "{i}/{n} ", "--size", "1KB"]);
+    let placeholder_err = placeholder.dispatch().1.err().unwrap();
+    assert!(placeholder_err.to_string().contains("{n}"));
+    let path = TempPath::new("last.jinja");
+    fs::write(&path, "{{ record }}{% if not record_last %},{% endif %}").unwrap();
+    let template = CliArgs::parse_from([
+        "",
+        "-p",
+        schema,
+        "--template",
+        path.to_str().unwrap(),
+        "--duration",
+        "5ms",
+    ]);
+    let template_err = template.dispatch().1.err().unwrap();
+    assert!(template_err.to_string().contains("record_last"));
+    fs::write(&path, "{{ last }},").unwrap();
+    let field = run([
+        "",
+        "-p",
+        r#"{"last": "LastName"}"#,
+        "--template",
+        path.to_str().unwrap(),
+        "--size",
+        "10B",
+    ]);
+    assert!(!field.starts_with("true") && !field.starts_with("false"));
+}
+
+#[test]
+fn rate() {
+    let schema = r#"{"name": "FirstName"}"#;
+    let start = Instant::now();
+    let out = run([
+        "", "-p", schema, "--format", "ndjson", "--rate", "100/s", "--burst", "2", "-c", "6",
+    ]);
+    assert_eq!(out.lines().count(), 6);
+    // The bursts are due after 0, 20 and 40 milliseconds.
+    assert!(start.elapsed() >= Duration::from_millis(40));
+    CliArgs::try_parse_from(["", "-p", schema, "--forever", "-c", "2"]).unwrap_err();
+    CliArgs::try_parse_from(["", "-p", schema, "--jitter", "10"]).unwrap_err();
+    CliArgs::try_parse_from(["", "-p", schema, "--rate", "500"]).unwrap_err();
+}
+
+#[test]
+fn output_files() {
+    let dir = TempPath::new("out");
+    let out = dir.join("out.json");
+    let schema = r#"{"id": "0..1000", "name": "FirstName"}"#;
+    assert_eq!(
+        run([
+            "",
+            "-p",
+            schema,
+            "-c",
+            "5",
+            "--format",
+            "json-array",
+            "-o",
+            out.to_str().unwrap(),
+            "--split-every",
+            "2",
+        ]),
+        ""
+    );
+    let sizes = ["out-00000.json", "out-00001.json", "out-00002.json"].map(|name| {
+        serde_json::from_slice::<Value>(&fs::read(dir.join(name)).unwrap())
+            .unwrap()
+            .as_array()
+            .map(Vec::len)
+    });
+    assert_eq!(sizes, [Some(2), Some(2), Some(1)]);
+    assert!(!dir.join("out-00003.json").exists());
+
+    #[expect(
+        clippy::literal_string_with_formatting_args,
+        reason = "output placeholders"
+    )]
+    let users = dir.join("users").join("{i}-{name}.json");
+    run([
+        "",
+        "-p",
+        schema,
+        "-c",
+        "3",
+        "-s",
+        "1",
+        "--offset",
+        "7",
+        "-o",
+        users.to_str().unwrap(),
+    ]);
+    for index in 7u64..10u64 {
+        let record = run(["", "-p", schema, "-s", "1", "--offset", &index.to_string()]);
+        let value = serde_json::from_str::<Value>(&record).unwrap();
+        let name = value.get("name").and_then(Value::as_str).unwrap();
+        let path = dir.join("users").join(format!("{index}-{name}.json"));
+        assert_eq!(fs::read_to_string(path).unwrap(), record);
+    }
+
+    let run_err = |pattern: &str, user: &str, output: &Path| {
+        let mut sink = vec![];
+        CliArgs::parse_from([
+            "",
+            "-p",
+            pattern,
+            "-u",
+            user,
+            "-c",
+            "10",
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .dispatch()
+        .1
+        .and_then(|act| act.run(&mut sink))
+        .unwrap_err()
+        .to_string()
+    };
+    let escaping = dir.join("sub").join("{d}").join("escaped.json");
+    assert!(run_err(r#"{"d": "D"}"#, "D:..", &escaping).contains("`..` segment"));
+    assert!(!dir.join("escaped.json").exists());
+    let colliding = dir.join("keys").join("{k}.json");
+    assert!(run_err(r#"{"k": "K"}"#, "K:a|b", &colliding).contains("already exists"));
+}
+
+/// Connects to the local server on `port`, waiting for it to start.
+fn connect(port: u16) -> TcpStream {
+    (0u8..100u8)
+        .find_map(|_| {
+            TcpStream::connect(("127.0.0.1", port))
+                .inspect_err(|_err| thread::sleep(Duration::from_millis(10)))
+                .ok()
+        })
+        .unwrap()
+}
+
+/// Sends an HTTP request to the local server on `port`, and returns the
+/// status and the body of the response.
+fn request(port: u16, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = connect(port);
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, content) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, content.to_owned())
+}
+
+/// Starts `random-json serve` with the given arguments on a free port, and
+/// returns the port.
+fn serve(args: &[&str]) -> u16 {
+    let port = TcpListener::bind(("127.0.0.1", 0))
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let mut full_args = vec![
+        String::new(),
+        "serve".to_owned(),
+        "--port".to_owned(),
+        port.to_string(),
+    ];
+    full_args.extend(args.iter().map(|&arg| arg.to_owned()));
+    thread::spawn(move || run_bytes(full_args));
+    port
+}
+
+#[test]
+fn serve_rest() {
+    let path = TempPath::new("user.json");
+    fs::write(&path, r#"{"name": "FirstName", "age": "18..99"}"#).unwrap();
+    let route = format!("/users={}", path.display());
+    let port = serve(&["-r", &route, "-s", "1", "-c", "25"]);
+
+    let (status, page) = request(port, "GET", "/users?page=3&per_page=10", "");
+    assert_eq!(status, 200);
+    let records = serde_json::from_str::<Vec<Value>>(&page).unwrap();
+    let ids = records
+        .iter()
+        .map(|record| record.get("id").and_then(Value::as_u64))
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [Some(20), Some(21), Some(22), Some(23), Some(24)]);
+
+    let (_, record) = request(port, "GET", "/users/22", "");
+    assert_eq!(records.get(2), Some(&serde_json::from_str(&record).unwrap()));
+    assert_eq!(request(port, "GET", "/users/25", "").0, 404);
+    assert_eq!(request(port, "GET", "/posts", "").0, 404);
+    assert_eq!(request(port, "GET", "/users?per_page=0", "").0, 400);
+}
+
+#[test]
+fn serve_crud() {
+    let path = TempPath::new("crud.json");
+    fs::write(&path, r#"{"name": "FirstName", "age": "18..99"}"#).unwrap();
+    let route = format!("/users={}", path.display());
+    let port = serve(&["-r", &route, "-c", "5", "--crud"]);
+
+    let (status, created) = request(port, "POST", "/users", r#"{"name": "Ada", "age": 36}"#);
+    assert_eq!(status, 201);
+    assert_eq!(
+        serde_json::from_str::<Value>(&created).unwrap().get("id"),
+        Some(&Value::from(5u64))
+    );
+    let (_, patched) = request(port, "PATCH", "/users/5", r#"{"age": 37}"#);
+    assert_eq!(
+        serde_json::from_str::<Value>(&patched).unwrap(),
+        serde_json::json!({"name": "Ada", "age": 37u64, "id": 5u64})
+    );
+    assert_eq!(request(port, "PUT", "/users/0", r#"{"name": "Bob"}"#).0, 200);
+    assert_eq!(request(port, "DELETE", "/users/1", "").0, 204);
+    assert_eq!(request(port, "GET", "/users/1", "").0, 404);
+
+    let (_, found) = request(port, "GET", "/users?name=Ada", "");
+    assert_eq!(serde_json::from_str::<Vec<Value>>(&found).unwrap().len(), 1);
+    let (_, sorted) = request(port, "GET", "/users?sort=-id&per_page=2", "");
+    let ids = serde_json::from_str::<Vec<Value>>(&sorted)
+        .unwrap()
+        .iter()
+        .map(|record| record.get("id").and_then(Value::as_u64))
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [Some(5), Some(4)]);
+}
+
+#[test]
+fn serve_events() {
+    let path = TempPath::new("tick.json");
+    fs::write(&path, r#"{"price": "1..1000"}"#).unwrap();
+    let route = format!("/ticks={}", path.display());
+    let port = serve(&["-e", &route, "-s", "1", "--rate", "100/s"]);
+
+    let mut stream = connect(port);
+    write!(stream, "GET /ticks HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let sse = BufReader::new(stream)
+        .lines()
+        .filter_map(|line| line.unwrap().strip_prefix("data: ").map(str::to_owned))
+        .take(3)
+        .collect::<Vec<_>>();
+
+    let (mut socket, _) =
+        tungstenite::client(format!("ws://127.0.0.1:{port}/ticks"), connect(port)).unwrap();
+    let websocket = repeat_with(|| {
+        socket
+            .read()
+            .unwrap()
+            .into_text()
+            .unwrap()
+            .as_str()
+            .to_owned()
+    })
+    .take(3)
+    .collect::<Vec<_>>();
+    assert_eq!(sse, websocket);
+}
+
+#[test]
+fn post() {
+    let path = TempPath::new("post.json");
+    fs::write(&path, "{}").unwrap();
+    let route = format!("/users={}", path.display());
+    let port = serve(&["-r", &route, "-c", "0", "--crud"]);
+    assert_eq!(request(port, "GET", "/users", "").0, 200);
+
+    let schema = r#"{"name": "FirstName"}"#;
+    let url = format!("http://127.0.0.1:{port}/users");
+    let summary = run([
+        "",
+        "-p",
+        schema,
+        "-s",
+        "1",
+        "-c",
+        "5",
+        "--post",
+        url.as_str(),
+        "--concurrency",
+        "2",
+        "-H",
+        "X-Source: test",
+    ]);
+    assert_eq!(summary, format!("Sent 5 of 5 records to {url} in 5 requests\n"));
+    let (_, listed) = request(port, "GET", "/users", "");
+    let mut posted = serde_json::from_str::<Vec<Value>>(&listed)
+        .unwrap()
+        .iter()
+        .map(|record| record.get("name").unwrap().to_string())
+        .collect::<Vec<_>>();
+    let mut generated = run(["", "-p", schema, "-s", "1", "-c", "5", "--format", "ndjson"])
+        .lines()
+        .map(|line| {
+            serde_json::from_str::<Value>(line)
+                .unwrap()
+                .get("name")
+                .unwrap()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    posted.sort();
+    generated.sort();
+    assert_eq!(posted, generated);
+
+    // Stub endpoint that always fails with a server error.
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let stub = format!("http://{}/", listener.local_addr().unwrap());
+    let bodies = thread::spawn(move || {
+        listener
+            .incoming()
+            .take(4)
+            .map(|stream| {
+                let mut reader = BufReader::new(stream.unwrap());
+                let mut length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+                serde_json::from_slice::<Vec<Value>>(&body).unwrap().len()
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut out = vec![];
+    let err = CliArgs::parse_from([
+        "",
+        "-p",
+        r#""Word""#,
+        "-c",
+        "3",
+        "--batch-size",
+        "2",
+        "--post",
+        stub.as_str(),
+        "--retries",
+        "1",
+    ])
+    .dispatch()
+    .1
+    .and_then(|act| act.run(&mut out))
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "2 of 2 requests failed, losing 3 records: 503 Service Unavailable (2)"
+    );
+    assert_eq!(bodies.join().unwrap(), [2, 2, 1, 1]);
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        format!("Sent 0 of 3 records to {stub} in 2 requests\n")
+    );
+    CliArgs::try_parse_from(["", "-p", schema, "--retries", "1"]).unwrap_err();
+}
+
+#[test]
+fn sqlite() {
+    let path = TempPath::new("sqlite.db");
+    let path_str = path.to_str().unwrap();
+    let schema = r#"{"id": "0..100", "name": "FirstName", "email?": "Email", "tags": ["Word", 2]}"#;
+    assert_eq!(
+        run([
+            "", "-p", schema, "-c", "50", "--sqlite", path_str, "--table", "users"
+        ]),
+        ""
+    );
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    let count: i64 = connection
+        .query_row("SELECT COUNT(*) FROM users WHERE id < 100 AND name != ''", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(count, 50);
+}